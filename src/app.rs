@@ -1,16 +1,28 @@
 pub mod audio;
+pub mod control;
+pub mod detail;
+pub mod devices;
 pub mod download;
 pub mod input;
+#[cfg(feature = "mpris")]
+pub mod mpris;
 pub mod navigation;
+pub mod pomodoro;
 pub mod presets;
+pub mod sleep;
 
-use crate::audio::AudioEngine;
-use crate::config::Config;
+use crate::audio::device::Backoff;
+use crate::audio::Engine;
+use crate::config::{Backend, Config};
+use crate::ipc::RemoteEngine;
+use crate::paths::Paths;
 use crate::presets::PresetsConfig;
 use crate::session::{Session, SoundState};
-use crate::static_data::{check_assets, get_bundled_sounds, AssetStatus, Sound};
+use crate::static_data::{check_assets, load_catalog, AssetStatus, Sound};
 use anyhow::Result;
 pub use download::{DownloadEvent, DownloadStatus, DownloadTask};
+pub use pomodoro::{Pomodoro, PomodoroPhase};
+pub use sleep::SleepTimer;
 use std::sync::mpsc::Receiver;
 
 pub enum AssetDownloadEvent {
@@ -23,6 +35,8 @@ pub enum CurrentView {
     Main,
     Presets,
     Help,
+    Detail,
+    Devices,
     Downloads,
     AssetMissing,
     DownloadingAssets,
@@ -32,7 +46,11 @@ pub struct App {
     pub sounds: Vec<Sound>,
     pub cursor_pos: usize,
     pub view: CurrentView,
-    pub audio_engine: Option<AudioEngine>,
+    pub audio_engine: Option<Box<dyn Engine>>,
+    // Playback happens in a daemon, audio_engine only forwards to it
+    pub attached: bool,
+    // Where the config, session and presets below are saved
+    pub paths: Paths,
     pub config: Config,
     pub session: Session,
     pub presets_config: PresetsConfig,
@@ -65,6 +83,19 @@ pub struct App {
     pub search_query: String,
     pub search_mode: bool,
 
+    // Sound detail popup state
+    pub detail_cursor: usize,
+    pub detail_changed: bool,
+
+    // Output device picker state, None stands for the automatic choice
+    pub device_list: Vec<Option<crate::audio::device::OutputDevice>>,
+    pub device_cursor: usize,
+    pub device_error: Option<String>,
+    // Why the configured output couldn't be used, shown until another one works
+    pub audio_error: Option<String>,
+    // Counts down to the next try while no output could be opened
+    pub audio_retry: Option<Backoff>,
+
     // Download Queue
     pub yt_dlp_available: bool,
     pub download_queue: Vec<DownloadTask>,
@@ -74,18 +105,52 @@ pub struct App {
     // Asset Download
     pub asset_download_rx: Option<Receiver<AssetDownloadEvent>>,
     pub asset_download_error: Option<String>,
+
+    pub sleep_timer: Option<SleepTimer>,
+    pub pomodoro: Option<Pomodoro>,
+
+    #[cfg(feature = "mpris")]
+    pub mpris: Option<crate::mpris::Mpris>,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let config = Config::load()?;
-        let session = Session::load()?;
-        let presets_config = PresetsConfig::load().unwrap_or_default();
+    /// Loads everything from `paths`. `backend` overrides the configured
+    /// output for this run only, and `mpris` offers media controls on the
+    /// session bus unless attached to a daemon, which offers its own.
+    #[cfg_attr(not(feature = "mpris"), allow(unused_variables))]
+    pub fn new(paths: Paths, attach: bool, backend: Option<Backend>, mpris: bool) -> Result<Self> {
+        let mut config = Config::load(&paths.config)?;
+        if let Some(backend) = backend {
+            config.audio.backend = backend;
+        }
+        let session = Session::load(&paths.session)?;
+        let presets_config = PresetsConfig::load(&paths.presets).unwrap_or_default();
 
-        let audio_engine = AudioEngine::new().ok();
+        let remote = if attach {
+            RemoteEngine::connect(&paths.socket).ok()
+        } else {
+            None
+        };
+
+        let mut daemon_status = None;
+        let mut audio_error = None;
+        let audio_engine: Option<Box<dyn Engine>> = match remote {
+            Some((engine, status)) => {
+                log::info!("Attached to running tanin daemon");
+                daemon_status = Some(status);
+                Some(Box::new(engine))
+            }
+            None => {
+                let (engine, error) = devices::start_engine(&config.audio, &paths.loudness);
+                audio_error = error;
+                engine.map(|engine| Box::new(engine) as Box<dyn Engine>)
+            }
+        };
+        let attached = daemon_status.is_some();
+        let audio_retry = (audio_engine.is_none() && !attached).then(Backoff::new);
 
         // Check yt-dlp availability
-        let yt_dlp_available = std::process::Command::new("yt-dlp")
+        let yt_dlp_available = std::process::Command::new(&paths.yt_dlp)
             .arg("--version")
             .stdout(std::process::Stdio::null())
             .stderr(std::process::Stdio::null())
@@ -98,6 +163,8 @@ impl App {
             cursor_pos: 0,
             view: CurrentView::Main,
             audio_engine,
+            attached,
+            paths,
             config: config.clone(),
             session: session.clone(),
             presets_config,
@@ -125,6 +192,15 @@ impl App {
             search_query: String::new(),
             search_mode: false,
 
+            detail_cursor: 0,
+            detail_changed: false,
+
+            device_list: Vec::new(),
+            device_cursor: 0,
+            device_error: None,
+            audio_error,
+            audio_retry,
+
             yt_dlp_available,
             download_queue: Vec::new(),
             active_download_index: None,
@@ -132,43 +208,73 @@ impl App {
 
             asset_download_rx: None,
             asset_download_error: None,
+
+            sleep_timer: None,
+            pomodoro: None,
+
+            // An attached TUI leaves media keys to the daemon
+            #[cfg(feature = "mpris")]
+            mpris: if attached || !mpris {
+                None
+            } else {
+                crate::mpris::Mpris::start()
+                    .map_err(|e| log::warn!("MPRIS unavailable: {}", e))
+                    .ok()
+            },
         };
 
-        if check_assets() == AssetStatus::Missing {
+        if check_assets(&app.paths.assets) == AssetStatus::Missing {
             app.view = CurrentView::AssetMissing;
-        } else if config.general.enable_bundled_sounds {
-            app.sounds.extend(get_bundled_sounds());
         }
+        app.sounds = load_catalog(&app.paths, &app.config.general);
 
-        app.sounds.extend(crate::static_data::load_custom_sounds());
-
-        // Sort all sounds to ensure categories are grouped correctly (merging bundled + custom)
-        app.sort_sounds();
+        // A daemon is already playing its own mix (and fetching its own files),
+        // adopt it instead of the saved session
+        if let Some(status) = daemon_status {
+            app.apply_status(&status);
+            return Ok(app);
+        }
 
         app.check_and_download_missing_files();
 
-        // Apply config
-        if let Some(engine) = &mut app.audio_engine {
-            engine.set_master_volume(session.global_volume);
-
-            for sound in &mut app.sounds {
-                if let Some(sc) = session.sounds.get(&sound.id) {
-                    sound.volume_linear = sc.volume;
-                    if sc.enabled {
-                        if let Err(e) =
-                            engine.play(&sound.id, &sound.file_path, sound.volume_linear)
-                        {
-                            log::error!("Failed to auto-play sound '{}': {}", sound.id, e);
-                            sound.error_state = true;
-                        }
-                    }
-                }
+        // Pick the countdown back up where the last run left it
+        app.sleep_timer = session.sleep_timer.map(|secs| SleepTimer {
+            remaining: std::time::Duration::from_secs(secs),
+            fading: false,
+        });
+
+        for sound in &mut app.sounds {
+            if let Some(sc) = session.sounds.get(&sound.id) {
+                sc.restore(sound);
             }
         }
+        app.play_session();
 
         Ok(app)
     }
 
+    /// Starts the sounds the saved session left playing.
+    pub fn play_session(&mut self) {
+        let Some(engine) = &mut self.audio_engine else {
+            return;
+        };
+        engine.set_master_volume(self.session.global_volume);
+
+        for sound in &mut self.sounds {
+            if self
+                .session
+                .sounds
+                .get(&sound.id)
+                .is_some_and(|sc| sc.enabled)
+            {
+                if let Err(e) = engine.play(sound, sound.volume_linear) {
+                    log::error!("Failed to auto-play sound '{}': {}", sound.id, e);
+                    sound.error_state = true;
+                }
+            }
+        }
+    }
+
     pub fn start_asset_download(&mut self) {
         let (tx, rx) = std::sync::mpsc::channel();
         self.asset_download_rx = Some(rx);
@@ -211,11 +317,7 @@ impl App {
                         }
 
                         // Reload sounds to pick up the new config
-                        if self.config.general.enable_bundled_sounds {
-                            self.sounds = get_bundled_sounds();
-                        }
-                        self.sounds.extend(crate::static_data::load_custom_sounds());
-                        self.sort_sounds();
+                        self.sounds = load_catalog(&self.paths, &self.config.general);
 
                         // Switch to Downloads view
                         self.view = CurrentView::Downloads;
@@ -243,6 +345,14 @@ impl App {
         if let Some(engine) = &mut self.audio_engine {
             engine.update(dt);
         }
+        self.retry_audio(dt);
+
+        self.update_sleep_timer(dt);
+        self.update_pomodoro(dt);
+
+        #[cfg(feature = "mpris")]
+        self.update_mpris();
+
         self.animation_offset += dt.as_secs_f32() * 3.0;
 
         // Queue Management
@@ -280,6 +390,7 @@ impl App {
 
                             // Keep URL in config
                             if let Err(e) = crate::static_data::add_custom_sound(
+                                &self.paths.sounds,
                                 &name,
                                 &cat,
                                 &path,
@@ -298,6 +409,14 @@ impl App {
                                     volume_linear: 0.5,
                                     icon,
                                     url: Some(url.clone()),
+                                    generator: None,
+                                    tone: None,
+                                    looping: Default::default(),
+                                    scatter: None,
+                                    spatial: Default::default(),
+                                    effects: Default::default(),
+                                    breathing: Default::default(),
+                                    defaults: Default::default(),
                                     error_state: false,
                                 };
                                 // Check if sound already exists (update case)
@@ -390,32 +509,7 @@ impl App {
     }
 
     pub fn sort_sounds(&mut self) {
-        let order = &self.config.general.category_order;
-        self.sounds.sort_by(|a, b| {
-            let pos_a = order.iter().position(|c| c == &a.category);
-            let pos_b = order.iter().position(|c| c == &b.category);
-
-            match (pos_a, pos_b) {
-                (Some(ia), Some(ib)) => {
-                    let cmp = ia.cmp(&ib);
-                    if cmp == std::cmp::Ordering::Equal {
-                        a.id.cmp(&b.id)
-                    } else {
-                        cmp
-                    }
-                }
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => {
-                    let cmp = a.category.cmp(&b.category);
-                    if cmp == std::cmp::Ordering::Equal {
-                        a.id.cmp(&b.id)
-                    } else {
-                        cmp
-                    }
-                }
-            }
-        });
+        crate::static_data::sort_sounds(&mut self.sounds, &self.config.general.category_order);
     }
 
     pub fn save_session(&mut self) {
@@ -423,7 +517,11 @@ impl App {
             let enabled = if let Some(engine) = &self.audio_engine {
                 engine.is_playing(&sound.id)
             } else {
-                false
+                // Audio never came up, keep the mix for the next start
+                self.session
+                    .sounds
+                    .get(&sound.id)
+                    .is_some_and(|sc| sc.enabled)
             };
 
             self.session.sounds.insert(
@@ -431,10 +529,218 @@ impl App {
                 SoundState {
                     enabled,
                     volume: sound.volume_linear,
+                    generator: sound.generator.filter(|g| g.is_tone()),
+                    tone: sound.tone,
+                    spatial: sound.spatial,
+                    effects: sound.effects,
+                    breathing: sound.breathing,
                 },
             );
         }
-        let _ = self.session.save();
-        let _ = self.presets_config.save();
+        // A timer that already ran out shouldn't fade the next start
+        self.session.sleep_timer = self
+            .sleep_timer
+            .as_ref()
+            .filter(|t| !t.fading)
+            .map(|t| t.remaining.as_secs());
+        let _ = self.session.save(&self.paths.session);
+        let _ = self.presets_config.save(&self.paths.presets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::output::PullOutput;
+    use crate::audio::testing::{self, assert_level, write_hum, RATE};
+    use crate::audio::AudioEngine;
+    use crate::ipc::{Request, Response, Server, Status};
+    use crate::presets::Preset;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::Duration;
+
+    // A sound playing only on the left and one only on the right
+    fn write_catalog(dir: &Path) {
+        std::fs::create_dir(dir.join("sounds")).unwrap();
+        write_hum(&dir.join("sounds").join("left.wav"), 0.5, 0.0);
+        write_hum(&dir.join("sounds").join("right.wav"), 0.0, 0.5);
+        std::fs::write(
+            dir.join("sounds.toml"),
+            "[Test.left]\nfile = \"left.wav\"\n\n[Test.right]\nfile = \"right.wav\"\n",
+        )
+        .unwrap();
+        let mut config = Config::default();
+        config.general.enable_bundled_sounds = false;
+        config.audio.limiter = false;
+        config.audio.crossfade_ms = 1000;
+        config.audio.volume_ramp_ms = 200;
+        config.save(&dir.join("config.toml")).unwrap();
+    }
+
+    /// An app playing the test catalog into an output the test pulls from.
+    fn app(dir: &Path) -> (App, PullOutput) {
+        write_catalog(dir);
+        let mut app = App::new(Paths::in_dir(dir), false, Some(Backend::Null), false).unwrap();
+        let output = PullOutput::new(RATE);
+        let engine = AudioEngine::with_output(
+            &app.config.audio,
+            &app.paths.loudness,
+            Box::new(output.clone()),
+        );
+        app.audio_engine = Some(Box::new(engine));
+        (app, output)
+    }
+
+    fn run(app: &mut App, output: &PullOutput, duration: Duration) -> Vec<(f32, f32)> {
+        let engine = app.audio_engine.as_deref_mut().unwrap();
+        testing::run(engine, output, duration)
+    }
+
+    /// A daemon on the app's socket that only knows the "Evening" preset,
+    /// passing on every request it answers.
+    fn fake_daemon(dir: &Path) -> Receiver<Request> {
+        let server = Server::bind(Paths::in_dir(dir).socket).unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for (request, reply) in server.rx.iter() {
+                let response = match &request {
+                    Request::Status => Response::Status(Status {
+                        master_volume: 1.0,
+                        muted: false,
+                        active_preset: None,
+                        sleep_remaining: None,
+                        sounds: Vec::new(),
+                    }),
+                    Request::LoadPreset { name } if name != "Evening" => Response::Error {
+                        message: format!("Unknown preset '{}'", name),
+                    },
+                    _ => Response::Ok,
+                };
+                let _ = tx.send(request);
+                let _ = reply.send(response);
+            }
+        });
+        rx
+    }
+
+    fn preset(name: &str, id: &str, volume: f32) -> Preset {
+        Preset {
+            name: name.to_string(),
+            sounds: HashMap::from([(id.to_string(), volume)]),
+            spatial: HashMap::new(),
+            effects: HashMap::new(),
+            breathing: HashMap::new(),
+        }
+    }
+
+    fn sound<'a>(app: &'a mut App, id: &str) -> &'a mut Sound {
+        app.sounds.iter_mut().find(|sound| sound.id == id).unwrap()
+    }
+
+    #[test]
+    fn presets_crossfade() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut app, output) = app(dir.path());
+        let left = sound(&mut app, "left").clone();
+        let engine = app.audio_engine.as_mut().unwrap();
+        engine.play(&left, 1.0).unwrap();
+        run(&mut app, &output, Duration::from_secs(2));
+        // Panned hard left the right sound would be silent, but the preset
+        // doesn't pan it so it goes back to the centre of the catalog
+        sound(&mut app, "right").spatial.pan = -1.0;
+
+        app.presets_config
+            .presets
+            .push(preset("Evening", "right", 0.6));
+        app.crossfade_to_preset(0);
+        assert_eq!(app.active_preset.as_deref(), Some("Evening"));
+        assert_eq!(sound(&mut app, "right").spatial.pan, 0.0);
+
+        let levels = run(&mut app, &output, Duration::from_millis(1500));
+        let (left, right) = levels[49];
+        assert_level(left, 0.5);
+        assert_level(right, 0.3);
+        let (left, right) = levels[99];
+        assert_level(left, 0.0);
+        assert_level(right, 0.6);
+        let (left, right) = levels[149];
+        assert_level(left, 0.0);
+        assert_level(right, 0.6);
+
+        let engine = app.audio_engine.as_ref().unwrap();
+        assert!(!engine.is_playing("left"));
+        assert!(engine.is_playing("right"));
+    }
+
+    #[test]
+    fn attached_presets_load_on_the_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        write_catalog(dir.path());
+        let requests = fake_daemon(dir.path());
+        let mut app =
+            App::new(Paths::in_dir(dir.path()), true, Some(Backend::Null), false).unwrap();
+        assert!(app.attached);
+        app.presets_config.presets = vec![
+            preset("Evening", "right", 0.6),
+            preset("Morning", "left", 0.3),
+        ];
+
+        let left = sound(&mut app, "left").volume_linear;
+
+        app.crossfade_to_preset(0);
+        assert_eq!(app.active_preset.as_deref(), Some("Evening"));
+        assert_eq!(sound(&mut app, "right").volume_linear, 0.6);
+        // Refused by the daemon, so nothing changes
+        app.crossfade_to_preset(1);
+        assert_eq!(app.active_preset.as_deref(), Some("Evening"));
+        assert_eq!(sound(&mut app, "left").volume_linear, left);
+
+        let loaded: Vec<String> = requests
+            .try_iter()
+            .filter_map(|request| match request {
+                Request::Status => None,
+                Request::LoadPreset { name } => Some(name),
+                other => panic!("the daemon should crossfade itself, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(loaded, ["Evening", "Morning"]);
+    }
+
+    #[test]
+    fn finished_work_is_logged_to_the_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut app, _) = app(dir.path());
+        app.toggle_pomodoro();
+        let work = Duration::from_secs(app.config.pomodoro.work_minutes * 60);
+        app.update_pomodoro(work);
+
+        let log = std::fs::read_to_string(&app.paths.pomodoro_log).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains("\"minutes\":0"), "{log}");
+    }
+
+    #[test]
+    fn mute_silences_the_mix_and_comes_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut app, output) = app(dir.path());
+        let left = sound(&mut app, "left").clone();
+        app.audio_engine.as_mut().unwrap().play(&left, 1.0).unwrap();
+        app.set_master_volume(0.5);
+        let levels = run(&mut app, &output, Duration::from_secs(2));
+        assert_level(levels[199].0, 0.5);
+
+        app.toggle_mute();
+        assert!(app.muted);
+        let levels = run(&mut app, &output, Duration::from_millis(300));
+        assert_level(levels[9].0, 0.25);
+        assert_level(levels[29].0, 0.0);
+
+        app.toggle_mute();
+        assert!(!app.muted);
+        let levels = run(&mut app, &output, Duration::from_millis(300));
+        assert_level(levels[29].0, 0.5);
     }
 }
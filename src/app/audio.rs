@@ -1,26 +1,59 @@
 use super::App;
+use crate::ipc::{self, Request};
+use crate::static_data::{Breathing, Effects, Spatial};
+use anyhow::Result;
 
 impl App {
     pub fn toggle_current_sound(&mut self) {
-        if let Some(sound) = self.sounds.get_mut(self.cursor_pos) {
+        let index = self.cursor_pos;
+        let Some(id) = self.sounds.get(index).map(|s| s.id.clone()) else {
+            return;
+        };
+
+        let playing = match &self.audio_engine {
+            Some(engine) => engine.is_playing(&id),
+            None => return,
+        };
+
+        if playing {
+            self.stop_sound(index);
+        } else if let Err(e) = self.play_sound(index) {
+            log::error!("Failed to play sound '{}': {}", id, e);
+        }
+    }
+
+    pub fn play_sound(&mut self, index: usize) -> Result<()> {
+        let Some(sound) = self.sounds.get_mut(index) else {
+            anyhow::bail!("No sound at index {}", index);
+        };
+        let Some(engine) = &mut self.audio_engine else {
+            anyhow::bail!("No audio engine available");
+        };
+
+        log::info!("Starting sound '{}'", sound.id);
+        sound.error_state = false;
+        if let Err(e) = engine.play(sound, sound.volume_linear) {
+            sound.error_state = true;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn stop_sound(&mut self, index: usize) {
+        if let Some(sound) = self.sounds.get(index) {
             if let Some(engine) = &mut self.audio_engine {
-                if engine.is_playing(&sound.id) {
-                    log::info!("Stopping sound '{}'", sound.id);
-                    engine.stop(&sound.id);
-                } else {
-                    log::info!("Starting sound '{}'", sound.id);
-                    sound.error_state = false;
-                    if let Err(e) = engine.play(&sound.id, &sound.file_path, sound.volume_linear) {
-                        log::error!("Failed to play sound '{}': {}", sound.id, e);
-                        sound.error_state = true;
-                    }
-                }
+                log::info!("Stopping sound '{}'", sound.id);
+                engine.stop(&sound.id);
             }
         }
     }
 
     pub fn set_current_volume(&mut self, vol: f32) {
-        if let Some(sound) = self.sounds.get_mut(self.cursor_pos) {
+        self.set_sound_volume(self.cursor_pos, vol);
+    }
+
+    pub fn set_sound_volume(&mut self, index: usize, vol: f32) {
+        if let Some(sound) = self.sounds.get_mut(index) {
             sound.volume_linear = vol.clamp(0.0, 1.0);
             if let Some(engine) = &mut self.audio_engine {
                 engine.set_volume(&sound.id, sound.volume_linear);
@@ -28,6 +61,61 @@ impl App {
         }
     }
 
+    pub fn set_sound_spatial(&mut self, index: usize, spatial: Spatial) {
+        if let Some(sound) = self.sounds.get_mut(index) {
+            sound.spatial = spatial.clamped();
+            if let Some(engine) = &mut self.audio_engine {
+                engine.set_spatial(&sound.id, sound.spatial);
+            }
+        }
+    }
+
+    pub fn set_sound_effects(&mut self, index: usize, effects: Effects) {
+        if let Some(sound) = self.sounds.get_mut(index) {
+            sound.effects = effects.clamped();
+            if let Some(engine) = &mut self.audio_engine {
+                engine.set_effects(&sound.id, sound.effects);
+            }
+        }
+    }
+
+    pub fn set_sound_breathing(&mut self, index: usize, breathing: Breathing) {
+        if let Some(sound) = self.sounds.get_mut(index) {
+            sound.breathing = breathing.clamped();
+            if let Some(engine) = &mut self.audio_engine {
+                engine.set_breathing(&sound.id, sound.breathing);
+            }
+        }
+    }
+
+    /// Where a gain sits on the sliders, following `audio.volume_scale`.
+    pub fn slider_position(&self, gain: f32) -> f32 {
+        self.config.audio.gain_to_slider(gain)
+    }
+
+    pub fn set_current_volume_position(&mut self, position: f32) {
+        let gain = self.config.audio.slider_to_gain(position);
+        self.set_current_volume(gain);
+    }
+
+    /// Moves the selected sound's slider by `delta`, in slider units.
+    pub fn nudge_current_volume(&mut self, delta: f32) {
+        if let Some(sound) = self.sounds.get(self.cursor_pos) {
+            let position = self.slider_position(sound.volume_linear) + delta;
+            self.set_current_volume_position(position);
+        }
+    }
+
+    pub fn set_master_volume_position(&mut self, position: f32) {
+        let gain = self.config.audio.slider_to_gain(position);
+        self.set_master_volume(gain);
+    }
+
+    pub fn nudge_master_volume(&mut self, delta: f32) {
+        let position = self.slider_position(self.session.global_volume) + delta;
+        self.set_master_volume_position(position);
+    }
+
     pub fn set_master_volume(&mut self, vol: f32) {
         self.session.global_volume = vol.clamp(0.0, 1.0);
         if let Some(engine) = &mut self.audio_engine {
@@ -36,14 +124,29 @@ impl App {
     }
 
     pub fn toggle_mute(&mut self) {
-        if self.muted {
-            self.muted = false;
-            self.set_master_volume(self.previous_volume);
-        } else {
-            self.muted = true;
+        self.set_muted(!self.muted);
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        if self.attached {
+            // The daemon keeps the volume to go back to
+            self.muted = muted;
+            if let Err(e) = ipc::send(&self.paths.socket, &Request::SetMute { muted }) {
+                log::error!("Failed to set mute on daemon: {}", e);
+            }
+            return;
+        }
+
+        if muted == self.muted {
+            return;
+        }
+        if muted {
             self.previous_volume = self.session.global_volume;
             self.set_master_volume(0.0);
+        } else {
+            self.set_master_volume(self.previous_volume);
         }
+        self.muted = muted;
     }
 
     pub fn stop_all(&mut self) {
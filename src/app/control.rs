@@ -0,0 +1,132 @@
+use super::App;
+use super::SleepTimer;
+use crate::ipc::{Request, Response, SoundStatus, Status};
+use std::time::Duration;
+
+impl App {
+    /// Applies a request received over the control socket.
+    pub fn handle_request(&mut self, request: Request) -> Response {
+        log::debug!("Control request: {:?}", request);
+        let result = match request {
+            Request::Play { id, volume } => self.find_sound(&id).and_then(|index| {
+                if let Some(v) = volume {
+                    self.set_sound_volume(index, v);
+                }
+                self.play_sound(index)
+            }),
+            Request::Stop { id: Some(id) } => self.find_sound(&id).map(|index| {
+                self.stop_sound(index);
+            }),
+            Request::Stop { id: None } => {
+                self.stop_all();
+                Ok(())
+            }
+            Request::SetVolume { id, volume } => self.find_sound(&id).map(|index| {
+                self.set_sound_volume(index, volume);
+            }),
+            Request::SetMasterVolume { volume } => {
+                self.muted = false;
+                self.set_master_volume(volume);
+                Ok(())
+            }
+            Request::SetMute { muted } => {
+                self.set_muted(muted);
+                Ok(())
+            }
+            Request::LoadPreset { name } => self.find_preset(&name).map(|index| {
+                self.load_preset(index);
+            }),
+            Request::SetTone {
+                id,
+                generator,
+                tone,
+            } => self
+                .find_sound(&id)
+                .and_then(|index| self.set_sound_tone(index, generator, tone)),
+            Request::SetSpatial { id, spatial } => self.find_sound(&id).map(|index| {
+                self.set_sound_spatial(index, spatial);
+            }),
+            Request::SetEffects { id, effects } => self.find_sound(&id).map(|index| {
+                self.set_sound_effects(index, effects);
+            }),
+            Request::SetBreathing { id, breathing } => self.find_sound(&id).map(|index| {
+                self.set_sound_breathing(index, breathing);
+            }),
+            Request::SetOutputDevice { host, device } => self.select_output_device(host, device),
+            Request::SetSleepTimer { seconds } => {
+                self.set_sleep_timer(seconds.map(Duration::from_secs));
+                Ok(())
+            }
+            Request::Status => return Response::Status(self.status()),
+            Request::Shutdown => {
+                self.quitting = true;
+                Ok(())
+            }
+        };
+
+        match result {
+            Ok(()) => Response::Ok,
+            Err(e) => Response::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            master_volume: self.session.global_volume,
+            muted: self.muted,
+            active_preset: self.active_preset.clone(),
+            sleep_remaining: self
+                .sleep_timer
+                .as_ref()
+                .filter(|t| !t.fading)
+                .map(|t| t.remaining.as_secs()),
+            sounds: self
+                .sounds
+                .iter()
+                .map(|sound| SoundStatus {
+                    id: sound.id.clone(),
+                    name: sound.name.clone(),
+                    category: sound.category.clone(),
+                    volume: sound.volume_linear,
+                    playing: self
+                        .audio_engine
+                        .as_ref()
+                        .is_some_and(|engine| engine.is_playing(&sound.id)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Takes over the state of a daemon the TUI has just attached to.
+    pub fn apply_status(&mut self, status: &Status) {
+        self.session.global_volume = status.master_volume;
+        self.muted = status.muted;
+        self.active_preset = status.active_preset.clone();
+        self.sleep_timer = status.sleep_remaining.map(|secs| SleepTimer {
+            remaining: Duration::from_secs(secs),
+            fading: false,
+        });
+
+        for sound in &mut self.sounds {
+            if let Some(remote) = status.sounds.iter().find(|s| s.id == sound.id) {
+                sound.volume_linear = remote.volume;
+            }
+        }
+    }
+
+    /// Looks a sound up by id or display name, ignoring case.
+    pub fn find_sound(&self, query: &str) -> anyhow::Result<usize> {
+        crate::static_data::find_sound(&self.sounds, query)
+            .ok_or_else(|| anyhow::anyhow!("Unknown sound '{}'", query))
+    }
+
+    pub fn find_preset(&self, name: &str) -> anyhow::Result<usize> {
+        self.presets_config
+            .presets
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("Unknown preset '{}'", name))
+    }
+}
@@ -0,0 +1,238 @@
+use super::{App, CurrentView};
+use crate::static_data::{Effects, Generator, ToneParams, Waveform};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetailField {
+    Pan,
+    Width,
+    LowPass,
+    HighPass,
+    Bass,
+    Mid,
+    Treble,
+    Breathing,
+    BreathPeriod,
+    Mode,
+    Carrier,
+    Beat,
+    Waveform,
+}
+
+impl DetailField {
+    pub fn label(self) -> &'static str {
+        match self {
+            DetailField::Pan => "Pan",
+            DetailField::Width => "Width",
+            DetailField::LowPass => "Low-pass",
+            DetailField::HighPass => "High-pass",
+            DetailField::Bass => "Bass",
+            DetailField::Mid => "Mid",
+            DetailField::Treble => "Treble",
+            DetailField::Breathing => "Breathing",
+            DetailField::BreathPeriod => "Breath",
+            DetailField::Mode => "Mode",
+            DetailField::Carrier => "Carrier",
+            DetailField::Beat => "Beat",
+            DetailField::Waveform => "Waveform",
+        }
+    }
+}
+
+impl App {
+    /// The editable parameters of the sound under the cursor.
+    pub fn detail_fields(&self) -> Vec<DetailField> {
+        let Some(sound) = self.sounds.get(self.cursor_pos) else {
+            return Vec::new();
+        };
+
+        let mut fields = vec![
+            DetailField::Pan,
+            DetailField::Width,
+            DetailField::LowPass,
+            DetailField::HighPass,
+            DetailField::Bass,
+            DetailField::Mid,
+            DetailField::Treble,
+            DetailField::Breathing,
+            DetailField::BreathPeriod,
+        ];
+        if sound.generator.is_some_and(Generator::is_tone) {
+            fields.extend([
+                DetailField::Mode,
+                DetailField::Carrier,
+                DetailField::Beat,
+                DetailField::Waveform,
+            ]);
+        }
+        fields
+    }
+
+    /// The current value of a parameter, formatted for the popup.
+    pub fn detail_value(&self, field: DetailField) -> String {
+        let Some(sound) = self.sounds.get(self.cursor_pos) else {
+            return String::new();
+        };
+        let tone = sound.tone.unwrap_or_default();
+        let pan = sound.spatial.pan;
+        let effects = sound.effects;
+        match field {
+            DetailField::Pan if pan.abs() < 0.05 => "Center".to_string(),
+            DetailField::Pan if pan < 0.0 => format!("L {:.0}%", -pan * 100.0),
+            DetailField::Pan => format!("R {:.0}%", pan * 100.0),
+            DetailField::Width => format!("{:.0}%", sound.spatial.width * 100.0),
+            DetailField::LowPass if effects.low_pass >= Effects::LOW_PASS_OFF => "Off".to_string(),
+            DetailField::LowPass => format_hz(effects.low_pass),
+            DetailField::HighPass if effects.high_pass <= Effects::HIGH_PASS_OFF => {
+                "Off".to_string()
+            }
+            DetailField::HighPass => format_hz(effects.high_pass),
+            DetailField::Bass => format!("{:+.0} dB", effects.low_db),
+            DetailField::Mid => format!("{:+.0} dB", effects.mid_db),
+            DetailField::Treble => format!("{:+.0} dB", effects.high_db),
+            DetailField::Breathing if !sound.breathing.is_active() => "Off".to_string(),
+            DetailField::Breathing => format!("down to -{:.0}%", sound.breathing.depth * 100.0),
+            DetailField::BreathPeriod => format!("every ~{:.0} s", sound.breathing.period),
+            DetailField::Mode => match sound.generator {
+                Some(Generator::Isochronic) => "Isochronic".to_string(),
+                _ => "Binaural".to_string(),
+            },
+            DetailField::Carrier => format!("{:.0} Hz", tone.carrier),
+            DetailField::Beat => format!("{:.1} Hz", tone.beat),
+            DetailField::Waveform => format!("{:?}", tone.waveform),
+        }
+    }
+
+    pub fn open_detail(&mut self) {
+        if self.cursor_pos < self.sounds.len() {
+            self.detail_cursor = 0;
+            self.detail_changed = false;
+            self.view = CurrentView::Detail;
+        }
+    }
+
+    /// Closes the popup and rebuilds the sound if a tone parameter changed.
+    /// Pan and width apply while the popup is open.
+    pub fn close_detail(&mut self) {
+        self.view = CurrentView::Main;
+        if !self.detail_changed {
+            return;
+        }
+        self.detail_changed = false;
+
+        let Some(sound) = self.sounds.get(self.cursor_pos) else {
+            return;
+        };
+        if let Some(engine) = &mut self.audio_engine {
+            if let Err(e) = engine.reload(sound) {
+                log::error!("Failed to apply changes to '{}': {}", sound.id, e);
+            }
+        }
+    }
+
+    pub fn set_sound_tone(
+        &mut self,
+        index: usize,
+        generator: Generator,
+        tone: ToneParams,
+    ) -> anyhow::Result<()> {
+        let Some(sound) = self.sounds.get_mut(index) else {
+            anyhow::bail!("No sound at index {}", index);
+        };
+        if !sound.generator.is_some_and(Generator::is_tone) || !generator.is_tone() {
+            anyhow::bail!("'{}' is not a tone generator", sound.id);
+        }
+
+        sound.generator = Some(generator);
+        sound.tone = Some(tone);
+        match &mut self.audio_engine {
+            Some(engine) => engine.reload(sound),
+            None => Ok(()),
+        }
+    }
+
+    pub fn move_detail_cursor(&mut self, delta: i32) {
+        let count = self.detail_fields().len();
+        if count == 0 {
+            return;
+        }
+        self.detail_cursor = (self.detail_cursor as i32 + delta).rem_euclid(count as i32) as usize;
+    }
+
+    /// Steps the selected parameter up or down.
+    pub fn adjust_detail_field(&mut self, steps: i32) {
+        let Some(&field) = self.detail_fields().get(self.detail_cursor) else {
+            return;
+        };
+        let Some(sound) = self.sounds.get_mut(self.cursor_pos) else {
+            return;
+        };
+
+        let steps = steps as f32;
+        let mut spatial = sound.spatial;
+        let mut effects = sound.effects;
+        let mut breathing = sound.breathing;
+        // Cutoffs move in quarter octaves, which feels even across the range
+        let octave_step = 2f32.powf(steps / 4.0);
+        // Only tone generators have tone fields, others must keep `tone` unset
+        let mut tone = sound.tone.unwrap_or_default();
+        match field {
+            DetailField::Pan => spatial.pan += steps * 0.1,
+            DetailField::Width => spatial.width += steps * 0.1,
+            DetailField::LowPass => effects.low_pass *= octave_step,
+            DetailField::HighPass => effects.high_pass *= octave_step,
+            DetailField::Bass => effects.low_db += steps,
+            DetailField::Mid => effects.mid_db += steps,
+            DetailField::Treble => effects.high_db += steps,
+            // Rounded so repeated steps land back on exactly 0.0 (off)
+            DetailField::Breathing => {
+                breathing.depth = ((breathing.depth + steps * 0.1) * 10.0).round() / 10.0
+            }
+            DetailField::BreathPeriod => breathing.period += steps * 15.0,
+            DetailField::Mode => {
+                sound.generator = match sound.generator {
+                    Some(Generator::Binaural) => Some(Generator::Isochronic),
+                    _ => Some(Generator::Binaural),
+                };
+            }
+            DetailField::Carrier => {
+                tone.carrier = (tone.carrier + steps * 5.0).clamp(40.0, 1000.0);
+            }
+            DetailField::Beat => {
+                tone.beat = (tone.beat + steps * 0.5).clamp(0.5, 40.0);
+            }
+            DetailField::Waveform => {
+                tone.waveform = match (tone.waveform, steps > 0.0) {
+                    (Waveform::Sine, true) | (Waveform::Square, false) => Waveform::Triangle,
+                    (Waveform::Triangle, true) | (Waveform::Sine, false) => Waveform::Square,
+                    (Waveform::Square, true) | (Waveform::Triangle, false) => Waveform::Sine,
+                };
+            }
+        }
+
+        match field {
+            DetailField::Pan | DetailField::Width => {
+                self.set_sound_spatial(self.cursor_pos, spatial)
+            }
+            DetailField::LowPass
+            | DetailField::HighPass
+            | DetailField::Bass
+            | DetailField::Mid
+            | DetailField::Treble => self.set_sound_effects(self.cursor_pos, effects),
+            DetailField::Breathing | DetailField::BreathPeriod => {
+                self.set_sound_breathing(self.cursor_pos, breathing)
+            }
+            _ => {
+                sound.tone = Some(tone);
+                self.detail_changed = true;
+            }
+        }
+    }
+}
+
+fn format_hz(hz: f32) -> String {
+    if hz >= 1000.0 {
+        format!("{:.1} kHz", hz / 1000.0)
+    } else {
+        format!("{:.0} Hz", hz)
+    }
+}
@@ -0,0 +1,167 @@
+use super::{App, CurrentView};
+use crate::audio::device::{list_output_devices, OutputDevice};
+use crate::audio::AudioEngine;
+use crate::config::{AudioConfig, Backend};
+use std::path::Path;
+use std::time::Duration;
+
+impl App {
+    pub fn open_device_picker(&mut self) {
+        // The first entry stands for the automatic choice
+        self.device_list = std::iter::once(None)
+            .chain(list_output_devices().into_iter().map(Some))
+            .collect();
+        self.device_cursor = self
+            .device_list
+            .iter()
+            .position(|d| self.is_selected_device(d.as_ref()))
+            .unwrap_or(0);
+        self.device_error = None;
+        self.view = CurrentView::Devices;
+    }
+
+    pub fn close_device_picker(&mut self) {
+        self.view = CurrentView::Main;
+        self.device_error = None;
+    }
+
+    pub fn move_device_cursor(&mut self, delta: i32) {
+        let count = self.device_list.len();
+        if count > 0 {
+            self.device_cursor =
+                (self.device_cursor as i32 + delta).rem_euclid(count as i32) as usize;
+        }
+    }
+
+    /// Whether an entry of the picker is the one in the config.
+    pub fn is_selected_device(&self, device: Option<&OutputDevice>) -> bool {
+        let audio = &self.config.audio;
+        match device {
+            None => audio.device.is_none() && audio.host.is_none(),
+            Some(d) => {
+                audio.device.as_deref() == Some(d.name.as_str())
+                    && audio
+                        .host
+                        .as_deref()
+                        .is_some_and(|host| host.eq_ignore_ascii_case(&d.host))
+            }
+        }
+    }
+
+    pub fn confirm_device_picker(&mut self) {
+        let Some(choice) = self.device_list.get(self.device_cursor).cloned() else {
+            return;
+        };
+        let (host, device) = match choice {
+            Some(d) => (Some(d.host), Some(d.name)),
+            None => (None, None),
+        };
+        match self.select_output_device(host, device) {
+            Ok(()) => self.close_device_picker(),
+            Err(e) => self.device_error = Some(e.to_string()),
+        }
+    }
+
+    /// Keeps trying to start audio when there was no output at startup, and
+    /// plays the saved session once it works.
+    pub fn retry_audio(&mut self, dt: Duration) {
+        let Some(backoff) = &mut self.audio_retry else {
+            return;
+        };
+        if !backoff.advance(dt) {
+            return;
+        }
+
+        let (engine, error) = start_engine(&self.config.audio, &self.paths.loudness);
+        self.audio_error = error;
+        match engine {
+            Some(engine) => {
+                log::info!("Audio output is available again");
+                self.audio_engine = Some(Box::new(engine));
+                self.audio_retry = None;
+                self.play_session();
+            }
+            None => {
+                backoff.failed();
+                let secs = backoff.delay().as_secs();
+                if let Some(error) = &mut self.audio_error {
+                    error.push_str(&format!(", retrying in {}s", secs));
+                }
+            }
+        }
+    }
+
+    /// Moves playback to another device, keeping every sound playing, and
+    /// remembers the choice in the config.
+    pub fn select_output_device(
+        &mut self,
+        host: Option<String>,
+        device: Option<String>,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.config.audio.backend == Backend::Cpal,
+            "There are no devices to pick while playing to {}",
+            self.config.audio.backend
+        );
+        match &mut self.audio_engine {
+            Some(engine) => engine.switch_device(host.as_deref(), device.as_deref())?,
+            None => {
+                // Starting without any device is a good reason to pick one
+                let mut audio = self.config.audio.clone();
+                audio.host = host.clone();
+                audio.device = device.clone();
+                self.audio_engine = Some(Box::new(AudioEngine::new(&audio, &self.paths.loudness)?));
+                self.audio_retry = None;
+                self.play_session();
+            }
+        }
+
+        log::info!("Switched audio output to {:?} on {:?}", device, host);
+        self.audio_error = None;
+        self.config.audio.host = host;
+        self.config.audio.device = device;
+        if let Err(e) = self.config.save(&self.paths.config) {
+            log::warn!("Failed to save the output device to the config: {}", e);
+        }
+        Ok(())
+    }
+}
+
+/// Starts the local engine on the configured output. When the configured
+/// device, rate or buffer size can't be used, it falls back to the defaults
+/// and returns the reason so the UI can show it. The null and recording
+/// outputs have nothing to fall back to.
+pub fn start_engine(
+    audio: &AudioConfig,
+    loudness_cache: &Path,
+) -> (Option<AudioEngine>, Option<String>) {
+    let error = match AudioEngine::new(audio, loudness_cache) {
+        Ok(engine) => return (Some(engine), None),
+        Err(e) => e,
+    };
+    let customized = audio.backend == Backend::Cpal
+        && (audio.host.is_some()
+            || audio.device.is_some()
+            || audio.sample_rate.is_some()
+            || audio.buffer_size.is_some());
+    if !customized {
+        log::error!("Failed to start audio: {:#}", error);
+        return (None, Some(format!("{:#}", error)));
+    }
+
+    log::warn!("{:#}, falling back to the default output", error);
+    let fallback = AudioConfig {
+        host: None,
+        device: None,
+        sample_rate: None,
+        buffer_size: None,
+        ..audio.clone()
+    };
+    let engine = AudioEngine::new(&fallback, loudness_cache)
+        .inspect_err(|e| log::error!("Failed to start audio: {:#}", e))
+        .ok();
+    (
+        engine,
+        Some(format!("{:#}, using the default output", error)),
+    )
+}
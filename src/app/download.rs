@@ -95,6 +95,7 @@ impl App {
         let url = task.url.clone();
         let target_filename = task.target_filename.clone();
         let yt_dlp_available = self.yt_dlp_available;
+        let yt_dlp = self.paths.yt_dlp.clone();
 
         thread::spawn(move || {
             let proj_dirs = match directories::ProjectDirs::from("com", "tanin", "tanin") {
@@ -228,7 +229,7 @@ impl App {
                 }
             }
 
-            let child = std::process::Command::new("yt-dlp")
+            let child = std::process::Command::new(&yt_dlp)
                 .arg("--ignore-config")
                 .arg("--no-playlist")
                 .arg("--force-overwrites")
@@ -0,0 +1,64 @@
+use super::App;
+use crate::mpris::{MprisCommand, MprisState};
+
+impl App {
+    /// Handles media key commands and republishes the player state.
+    pub fn update_mpris(&mut self) {
+        let Some(mpris) = &self.mpris else {
+            return;
+        };
+
+        let commands: Vec<MprisCommand> = mpris.rx.try_iter().collect();
+        for command in commands {
+            match command {
+                MprisCommand::PlayPause => self.toggle_mute(),
+                MprisCommand::Play if self.muted => self.toggle_mute(),
+                MprisCommand::Pause if !self.muted => self.toggle_mute(),
+                MprisCommand::Play | MprisCommand::Pause => {}
+                MprisCommand::Stop => self.stop_all(),
+                MprisCommand::SetVolume(volume) => {
+                    self.muted = false;
+                    self.set_master_volume_position(volume as f32);
+                }
+                MprisCommand::Quit => self.quitting = true,
+            }
+        }
+
+        let state = self.mpris_state();
+        if let Some(mpris) = &self.mpris {
+            mpris.publish(state);
+        }
+    }
+
+    fn mpris_state(&self) -> MprisState {
+        let artists: Vec<String> = self
+            .sounds
+            .iter()
+            .filter(|s| {
+                self.audio_engine
+                    .as_ref()
+                    .is_some_and(|engine| engine.is_playing(&s.id))
+            })
+            .map(|s| s.name.clone())
+            .collect();
+
+        let playback_status = if artists.is_empty() {
+            "Stopped"
+        } else if self.muted {
+            "Paused"
+        } else {
+            "Playing"
+        };
+
+        MprisState {
+            playback_status,
+            // Desktop widgets show it as a slider too
+            volume: self.slider_position(self.session.global_volume) as f64,
+            title: self
+                .active_preset
+                .clone()
+                .unwrap_or_else(|| "Tanin".to_string()),
+            artists,
+        }
+    }
+}
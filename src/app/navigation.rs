@@ -1,15 +1,17 @@
 use super::App;
+use crate::ui::main_view::{
+    CARD_GAP, CARD_HEIGHT, CARD_WIDTH, CATEGORY_HEIGHT, GRID_MARGIN, ROW_GAP,
+};
 
 impl App {
     pub fn update_grid_cols(&mut self) {
-        let card_width = 24; // 22 + 2 margin
-        let cols = (self.width.saturating_sub(4)) / card_width;
+        // As many cards as fit after the margin, the last without a gap
+        let cols = (self.width.saturating_sub(GRID_MARGIN) + CARD_GAP) / (CARD_WIDTH + CARD_GAP);
         self.grid_cols = if cols < 1 { 1 } else { cols };
     }
 
     pub fn get_sound_row_top(&self, index: usize) -> u16 {
         let mut current_y = 0;
-        let card_height = 5; // card height
         let cols = self.grid_cols as usize;
 
         let filtered = self.get_filtered_sounds();
@@ -24,29 +26,26 @@ impl App {
                 .map(|(i, _)| *i)
                 .collect();
 
-            // Header takes 2 rows
-            current_y += 2;
+            current_y += CATEGORY_HEIGHT;
 
             for chunk in cat_sounds.chunks(cols) {
                 // Check if our index is in this chunk
                 if chunk.contains(&index) {
                     return current_y;
                 }
-                // Height of row + 1 empty line
-                current_y += card_height + 1;
+                current_y += CARD_HEIGHT + ROW_GAP;
             }
         }
         0
     }
 
     pub fn scroll_into_view(&mut self) {
-        let card_height = 5;
         let viewport_height = self.height.saturating_sub(6); // 3 header + 3 footer
         let row_top = self.get_sound_row_top(self.cursor_pos);
-        let row_bottom = row_top + card_height;
+        let row_bottom = row_top + CARD_HEIGHT;
 
-        // Ensure we see the header (2 rows above) if we scroll up
-        let effective_top = row_top.saturating_sub(2);
+        // Ensure we see the category title above if we scroll up
+        let effective_top = row_top.saturating_sub(CATEGORY_HEIGHT);
 
         if effective_top < self.grid_scroll {
             self.grid_scroll = effective_top;
@@ -182,8 +181,7 @@ impl App {
         // Calculate max scroll
         let last_idx = filtered.last().map(|(idx, _)| *idx).unwrap_or(0);
         // get_sound_row_top gives top Y relative to start.
-        // + 5 (card height) + 1 (margin)
-        let content_height = self.get_sound_row_top(last_idx) as i32 + 6;
+        let content_height = (self.get_sound_row_top(last_idx) + CARD_HEIGHT + ROW_GAP) as i32;
         let viewport_height = self.height.saturating_sub(6) as i32;
 
         let max_scroll = if content_height > viewport_height {
@@ -0,0 +1,146 @@
+use super::App;
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PomodoroPhase {
+    Work,
+    Break,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    pub fn label(self) -> &'static str {
+        match self {
+            PomodoroPhase::Work => "Work",
+            PomodoroPhase::Break => "Break",
+            PomodoroPhase::LongBreak => "Long break",
+        }
+    }
+}
+
+pub struct Pomodoro {
+    pub phase: PomodoroPhase,
+    pub remaining: Duration,
+    // Work phases finished since the scheduler was started
+    pub completed: u32,
+    phase_started: SystemTime,
+}
+
+// One line of the session log
+#[derive(Serialize)]
+struct LoggedSession<'a> {
+    started: u64,
+    finished: u64,
+    minutes: u64,
+    preset: Option<&'a str>,
+}
+
+impl App {
+    pub fn toggle_pomodoro(&mut self) {
+        if self.pomodoro.take().is_some() {
+            log::info!("Pomodoro stopped");
+            return;
+        }
+
+        log::info!("Pomodoro started");
+        self.pomodoro = Some(Pomodoro {
+            phase: PomodoroPhase::Work,
+            remaining: Duration::ZERO,
+            completed: 0,
+            phase_started: SystemTime::now(),
+        });
+        self.enter_pomodoro_phase(PomodoroPhase::Work);
+    }
+
+    /// Ends the current phase early. Skipped work isn't logged.
+    pub fn skip_pomodoro_phase(&mut self) {
+        if let Some(pomodoro) = &self.pomodoro {
+            let next = self.next_pomodoro_phase(pomodoro.phase, pomodoro.completed);
+            self.enter_pomodoro_phase(next);
+        }
+    }
+
+    pub fn update_pomodoro(&mut self, dt: Duration) {
+        let Some(pomodoro) = &mut self.pomodoro else {
+            return;
+        };
+
+        pomodoro.remaining = pomodoro.remaining.saturating_sub(dt);
+        if !pomodoro.remaining.is_zero() {
+            return;
+        }
+
+        if pomodoro.phase == PomodoroPhase::Work {
+            pomodoro.completed += 1;
+            let started = pomodoro.phase_started;
+            let preset = self.config.pomodoro.work_preset.clone();
+            if let Err(e) = log_session(&self.paths.pomodoro_log, started, preset.as_deref()) {
+                log::error!("Failed to log pomodoro session: {}", e);
+            }
+        }
+
+        let (phase, completed) = (pomodoro.phase, pomodoro.completed);
+        let next = self.next_pomodoro_phase(phase, completed);
+        self.enter_pomodoro_phase(next);
+    }
+
+    fn next_pomodoro_phase(&self, phase: PomodoroPhase, completed: u32) -> PomodoroPhase {
+        let every = self.config.pomodoro.long_break_every;
+        match phase {
+            PomodoroPhase::Work if every > 0 && completed > 0 && completed % every == 0 => {
+                PomodoroPhase::LongBreak
+            }
+            PomodoroPhase::Work => PomodoroPhase::Break,
+            PomodoroPhase::Break | PomodoroPhase::LongBreak => PomodoroPhase::Work,
+        }
+    }
+
+    fn enter_pomodoro_phase(&mut self, phase: PomodoroPhase) {
+        let config = &self.config.pomodoro;
+        let (minutes, preset) = match phase {
+            PomodoroPhase::Work => (config.work_minutes, config.work_preset.clone()),
+            PomodoroPhase::Break => (config.break_minutes, config.break_preset.clone()),
+            PomodoroPhase::LongBreak => (config.long_break_minutes, config.break_preset.clone()),
+        };
+
+        if let Some(pomodoro) = &mut self.pomodoro {
+            pomodoro.phase = phase;
+            pomodoro.remaining = Duration::from_secs(minutes.max(1) * 60);
+            pomodoro.phase_started = SystemTime::now();
+        }
+        log::info!("Pomodoro phase: {}", phase.label());
+
+        if let Some(name) = preset {
+            match self.find_preset(&name) {
+                Ok(index) => self.crossfade_to_preset(index),
+                Err(e) => log::warn!("Pomodoro: {}", e),
+            }
+        }
+    }
+}
+
+fn log_session(path: &Path, started: SystemTime, preset: Option<&str>) -> Result<()> {
+    let finished = SystemTime::now();
+    let entry = LoggedSession {
+        started: started.duration_since(UNIX_EPOCH)?.as_secs(),
+        finished: finished.duration_since(UNIX_EPOCH)?.as_secs(),
+        minutes: finished
+            .duration_since(started)
+            .unwrap_or_default()
+            .as_secs()
+            / 60,
+        preset,
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
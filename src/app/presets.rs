@@ -1,4 +1,8 @@
 use super::{App, CurrentView};
+use crate::ipc::{self, Request, Response};
+use crate::presets::Preset;
+use std::collections::HashMap;
+use std::time::Duration;
 
 impl App {
     pub fn confirm_preset_input(&mut self) {
@@ -14,29 +18,47 @@ impl App {
             }
         } else {
             // Create new
-            let mut preset_sounds = std::collections::HashMap::new();
-
-            // Capture currently playing sounds
-            if let Some(engine) = &self.audio_engine {
-                for sound in &self.sounds {
-                    if engine.is_playing(&sound.id) {
-                        preset_sounds.insert(sound.id.clone(), sound.volume_linear);
-                    }
-                }
-            }
-
-            let new_preset = crate::presets::Preset {
-                name,
-                sounds: preset_sounds,
-            };
-
+            let new_preset = self.capture_preset(name);
             self.presets_config.presets.push(new_preset);
         }
 
-        let _ = self.presets_config.save();
+        let _ = self.presets_config.save(&self.paths.presets);
         self.preset_rename_target = None;
     }
 
+    /// The playing sounds at their volumes, plus their pan, width, filters and
+    /// breathing where those differ from the catalog's.
+    fn capture_preset(&self, name: String) -> Preset {
+        let mut preset = Preset {
+            name,
+            sounds: HashMap::new(),
+            spatial: HashMap::new(),
+            effects: HashMap::new(),
+            breathing: HashMap::new(),
+        };
+        let Some(engine) = &self.audio_engine else {
+            return preset;
+        };
+
+        for sound in &self.sounds {
+            if !engine.is_playing(&sound.id) {
+                continue;
+            }
+            let id = sound.id.clone();
+            preset.sounds.insert(id.clone(), sound.volume_linear);
+            if sound.spatial != sound.defaults.spatial {
+                preset.spatial.insert(id.clone(), sound.spatial);
+            }
+            if sound.effects != sound.defaults.effects {
+                preset.effects.insert(id.clone(), sound.effects);
+            }
+            if sound.breathing != sound.defaults.breathing {
+                preset.breathing.insert(id, sound.breathing);
+            }
+        }
+        preset
+    }
+
     pub fn start_renaming_preset(&mut self) {
         if let Some(preset) = self.presets_config.presets.get(self.preset_cursor_pos) {
             self.preset_input_buffer = preset.name.clone();
@@ -50,19 +72,11 @@ impl App {
             return;
         }
 
-        let mut preset_sounds = std::collections::HashMap::new();
-        if let Some(engine) = &self.audio_engine {
-            for sound in &self.sounds {
-                if engine.is_playing(&sound.id) {
-                    preset_sounds.insert(sound.id.clone(), sound.volume_linear);
-                }
-            }
-        }
-
-        if let Some(preset) = self.presets_config.presets.get_mut(self.preset_cursor_pos) {
-            preset.sounds = preset_sounds;
+        if let Some(preset) = self.presets_config.presets.get(self.preset_cursor_pos) {
+            let updated = self.capture_preset(preset.name.clone());
+            self.presets_config.presets[self.preset_cursor_pos] = updated;
         }
-        let _ = self.presets_config.save();
+        let _ = self.presets_config.save(&self.paths.presets);
     }
 
     pub fn load_preset(&mut self, index: usize) {
@@ -70,35 +84,87 @@ impl App {
             return;
         }
 
-        // Clone the sounds map to avoid borrowing self while mutating self later
-        let preset_sounds = self.presets_config.presets[index].sounds.clone();
+        self.crossfade_to_preset(index);
+        self.view = CurrentView::Main;
+    }
 
-        self.stop_all();
+    /// Switches to a preset without cutting the mix: sounds that aren't part of
+    /// it fade out, new ones fade in and shared ones ramp to the preset volume.
+    pub fn crossfade_to_preset(&mut self, index: usize) {
+        let Some(preset) = self.presets_config.presets.get(index).cloned() else {
+            return;
+        };
+        if self.attached {
+            self.load_preset_on_daemon(preset);
+            return;
+        }
+        let duration = Duration::from_millis(self.config.audio.crossfade_ms);
 
-        // Need to update app.sounds volumes and play them
         if let Some(engine) = &mut self.audio_engine {
             for sound in &mut self.sounds {
-                if let Some(&vol) = preset_sounds.get(&sound.id) {
-                    sound.volume_linear = vol;
-                    sound.error_state = false;
-                    if let Err(e) = engine.play(&sound.id, &sound.file_path, sound.volume_linear) {
-                        log::error!("Failed to play preset sound '{}': {}", sound.id, e);
-                        sound.error_state = true;
+                let playing = engine.is_playing(&sound.id);
+                if preset.sounds.contains_key(&sound.id) {
+                    preset.apply(sound);
+                    engine.set_spatial(&sound.id, sound.spatial);
+                    engine.set_effects(&sound.id, sound.effects);
+                    engine.set_breathing(&sound.id, sound.breathing);
+                }
+                match preset.sounds.get(&sound.id) {
+                    Some(&vol) if playing => {
+                        sound.volume_linear = vol;
+                        engine.ramp_volume(&sound.id, vol, duration);
+                    }
+                    Some(&vol) => {
+                        sound.volume_linear = vol;
+                        sound.error_state = false;
+                        // Start silent and ramp up so the fade-in follows the crossfade
+                        match engine.play(sound, 0.0) {
+                            Ok(()) => engine.ramp_volume(&sound.id, vol, duration),
+                            Err(e) => {
+                                log::error!("Failed to play preset sound '{}': {}", sound.id, e);
+                                sound.error_state = true;
+                            }
+                        }
                     }
-                } else {
-                    // Optionally reset volume of unused sounds or keep them as is?
-                    // Usually presets define the active set.
+                    None if playing => engine.fade_out(&sound.id, duration),
+                    None => {}
                 }
             }
         }
-        self.active_preset = Some(self.presets_config.presets[index].name.clone());
-        self.view = CurrentView::Main;
+        self.active_preset = Some(preset.name);
+    }
+
+    // The daemon crossfades its own engine, the TUI only mirrors the result
+    fn load_preset_on_daemon(&mut self, preset: Preset) {
+        let request = Request::LoadPreset {
+            name: preset.name.clone(),
+        };
+        let message = match ipc::send(&self.paths.socket, &request) {
+            Ok(Response::Error { message }) => message,
+            Ok(_) => {
+                for sound in &mut self.sounds {
+                    if let Some(&vol) = preset.sounds.get(&sound.id) {
+                        preset.apply(sound);
+                        sound.volume_linear = vol;
+                        sound.error_state = false;
+                    }
+                }
+                self.active_preset = Some(preset.name);
+                return;
+            }
+            Err(e) => e.to_string(),
+        };
+        log::error!(
+            "Failed to load preset '{}' on daemon: {}",
+            preset.name,
+            message
+        );
     }
 
     pub fn delete_preset(&mut self, index: usize) {
         if index < self.presets_config.presets.len() {
             self.presets_config.presets.remove(index);
-            let _ = self.presets_config.save();
+            let _ = self.presets_config.save(&self.paths.presets);
             if self.preset_cursor_pos >= self.presets_config.presets.len()
                 && !self.presets_config.presets.is_empty()
             {
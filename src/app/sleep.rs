@@ -0,0 +1,107 @@
+use super::App;
+use crate::ipc::{self, Request};
+use std::time::Duration;
+
+// Steps the sleep timer key cycles through, in minutes
+const SLEEP_STEPS: [u64; 6] = [15, 30, 45, 60, 90, 120];
+
+pub struct SleepTimer {
+    pub remaining: Duration,
+    // Set once the countdown is over and the mix is fading out
+    pub fading: bool,
+}
+
+impl App {
+    pub fn set_sleep_timer(&mut self, duration: Option<Duration>) {
+        if let Some(engine) = &mut self.audio_engine {
+            engine.cancel_master_fade();
+        }
+
+        self.sleep_timer = duration.map(|remaining| SleepTimer {
+            remaining,
+            fading: false,
+        });
+        self.session.sleep_timer = duration.map(|d| d.as_secs());
+
+        if self.attached {
+            let request = Request::SetSleepTimer {
+                seconds: duration.map(|d| d.as_secs()),
+            };
+            if let Err(e) = ipc::send(&self.paths.socket, &request) {
+                log::error!("Failed to set sleep timer on daemon: {}", e);
+            }
+        }
+    }
+
+    /// Moves to the next preset duration above the current one, then off.
+    pub fn cycle_sleep_timer(&mut self) {
+        let current = self
+            .sleep_timer
+            .as_ref()
+            .filter(|t| !t.fading)
+            .map(|t| t.remaining);
+
+        let next = match current {
+            None => SLEEP_STEPS.first().copied(),
+            Some(remaining) => SLEEP_STEPS
+                .iter()
+                .copied()
+                .find(|&m| Duration::from_secs(m * 60) > remaining),
+        };
+
+        self.set_sleep_timer(next.map(|m| Duration::from_secs(m * 60)));
+    }
+
+    pub fn update_sleep_timer(&mut self, dt: Duration) {
+        let Some(timer) = &mut self.sleep_timer else {
+            return;
+        };
+
+        if timer.fading {
+            let still_fading = self
+                .audio_engine
+                .as_ref()
+                .is_some_and(|engine| engine.is_fading_out());
+            if !still_fading {
+                log::info!("Sleep timer finished");
+                self.sleep_timer = None;
+                self.session.sleep_timer = None;
+                if self.config.sleep.quit_on_expire {
+                    self.quitting = true;
+                }
+            }
+            return;
+        }
+
+        timer.remaining = timer.remaining.saturating_sub(dt);
+        self.session.sleep_timer = Some(timer.remaining.as_secs());
+        if !timer.remaining.is_zero() {
+            return;
+        }
+
+        // When attached, the daemon counts down too and does the fading
+        if self.attached {
+            self.sleep_timer = None;
+            self.session.sleep_timer = None;
+            return;
+        }
+
+        log::info!("Sleep timer expired, fading out");
+        timer.fading = true;
+        let fade = Duration::from_secs(self.config.sleep.fade_seconds);
+        if let Some(engine) = &mut self.audio_engine {
+            engine.fade_out_master(fade);
+        }
+    }
+}
+
+/// Formats a countdown as `m:ss`, or `h:mm:ss` from an hour up.
+pub fn format_remaining(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
@@ -1,158 +1,481 @@
-use anyhow::{Context, Result};
-use cpal::traits::{DeviceTrait, HostTrait};
-use cpal::HostId;
-use magnum::container::ogg::OpusSourceOgg;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+pub mod breathing;
+pub mod cache;
+pub mod decoder;
+pub mod device;
+pub mod effects;
+pub mod encoder;
+pub mod generators;
+pub mod limiter;
+pub mod looping;
+pub mod loudness;
+#[cfg(feature = "opus")]
+pub mod opus;
+pub mod output;
+pub mod render;
+pub mod scatter;
+pub mod spatial;
+#[cfg(test)]
+pub mod testing;
+#[cfg(feature = "opus")]
+pub mod webm;
+
+use crate::config::{AudioConfig, Backend, RampCurve};
+use crate::static_data::{Breathing, Effects, Sound, Spatial};
+use anyhow::Result;
+use breathing::Breath;
+use cache::SampleCache;
+use device::{Backoff, StreamSettings};
+use effects::{EffectsControl, EffectsSource};
+use loudness::Normalizer;
+use output::Output;
+use rodio::dynamic_mixer::{self, DynamicMixerController};
+use rodio::{Sink, Source};
+use spatial::{SpatialControl, SpatialSource};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 struct FadingSink {
     id: String,
     sink: Sink,
-    start_volume: f32,
+    ramp: VolumeRamp,
+}
+
+// Moves a volume towards a target over time instead of jumping to it
+struct VolumeRamp {
+    from: f32,
+    to: f32,
     elapsed: Duration,
     total_duration: Duration,
 }
 
-struct MagnumOggWrapper<R: std::io::Read + std::io::Seek>(OpusSourceOgg<R>);
+impl VolumeRamp {
+    fn new(from: f32, to: f32, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: Duration::ZERO,
+            total_duration: duration,
+        }
+    }
+
+    fn advance(&mut self, dt: Duration) {
+        self.elapsed += dt;
+    }
 
-impl<R: std::io::Read + std::io::Seek> Iterator for MagnumOggWrapper<R> {
-    type Item = f32;
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next()
+    fn is_done(&self) -> bool {
+        self.elapsed >= self.total_duration
+    }
+
+    fn value(&self, curve: RampCurve) -> f32 {
+        if self.is_done() {
+            return self.to;
+        }
+        let progress = self.elapsed.as_secs_f32() / self.total_duration.as_secs_f32();
+        let (from, to) = (self.from, self.to);
+
+        match curve {
+            RampCurve::Linear => from + (to - from) * progress,
+            // Quarter sine/cosine, so a fade in and a fade out overlapping keep
+            // the combined power steady
+            RampCurve::EqualPower => {
+                let angle = progress * std::f32::consts::FRAC_PI_2;
+                let shaped = if to >= from {
+                    angle.sin()
+                } else {
+                    1.0 - angle.cos()
+                };
+                from + (to - from) * shaped
+            }
+            // Even steps in dB, with silence treated as -60 dB
+            RampCurve::Exponential => {
+                const FLOOR: f32 = 0.001;
+                let (from, to) = (from.max(FLOOR), to.max(FLOOR));
+                from * (to / from).powf(progress)
+            }
+        }
     }
 }
 
-impl<R: std::io::Read + std::io::Seek> Source for MagnumOggWrapper<R> {
-    fn current_frame_len(&self) -> Option<usize> {
+/// The operations the app drives playback through. Implemented by the local
+/// `AudioEngine` and by `ipc::RemoteEngine` when the TUI is attached to a daemon.
+pub trait Engine {
+    fn update(&mut self, dt: Duration);
+    fn play(&mut self, sound: &Sound, volume: f32) -> Result<()>;
+    fn stop(&mut self, id: &str);
+    /// Rebuilds a playing sound after its parameters changed.
+    fn reload(&mut self, sound: &Sound) -> Result<()>;
+    fn set_volume(&mut self, id: &str, volume: f32);
+    fn set_master_volume(&mut self, volume: f32);
+    fn set_spatial(&mut self, id: &str, spatial: Spatial);
+    fn set_effects(&mut self, id: &str, effects: Effects);
+    /// Moves playback to another output device, `None` meaning automatic.
+    fn switch_device(&mut self, host: Option<&str>, device: Option<&str>) -> Result<()>;
+    fn set_breathing(&mut self, id: &str, breathing: Breathing);
+    fn is_playing(&self, id: &str) -> bool;
+    fn stop_all(&mut self);
+    /// Fades the whole mix out and then stops every sound.
+    fn fade_out_master(&mut self, duration: Duration);
+    fn cancel_master_fade(&mut self);
+    fn is_fading_out(&self) -> bool;
+
+    /// Why the output isn't playing while it is being reconnected.
+    fn output_error(&self) -> Option<&str> {
         None
     }
 
-    fn channels(&self) -> u16 {
-        2
+    /// How much breathing currently scales a sound, 1.0 when it is steady.
+    fn modulation(&self, _id: &str) -> f32 {
+        1.0
     }
 
-    fn sample_rate(&self) -> u32 {
-        48000
+    /// Stops a sound over `duration` instead of the default fade.
+    fn fade_out(&mut self, id: &str, _duration: Duration) {
+        self.stop(id);
     }
 
-    fn total_duration(&self) -> Option<Duration> {
-        None
+    /// Moves a playing sound to `volume` gradually over `duration`.
+    fn ramp_volume(&mut self, id: &str, volume: f32, _duration: Duration) {
+        self.set_volume(id, volume);
     }
 }
 
 pub struct AudioEngine {
-    _stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    stream: Box<dyn Output>,
+    backend: Backend,
+    // The output asked for, reopened with these after a failure
+    host: Option<String>,
+    device: Option<String>,
+    settings: StreamSettings,
+    output_error: Option<String>,
+    reconnect: Option<Backoff>,
+    // Every sink feeds this, and it plays through the limiter
+    mixer: Arc<DynamicMixerController<f32>>,
+    limiter: bool,
+    // Playing sounds with their current settings, to rebuild them on another device
+    playing: HashMap<String, Sound>,
+    normalizer: Option<Normalizer>,
+    cache: SampleCache,
     sinks: HashMap<String, Sink>,
     fading_sinks: Vec<FadingSink>,
     master_volume: f32,
+    master_ramp: Option<VolumeRamp>,
+    // Scales the whole mix down to silence, e.g. when the sleep timer runs out
+    master_fade: Option<VolumeRamp>,
     sound_volumes: HashMap<String, f32>,
+    volume_ramps: HashMap<String, VolumeRamp>,
+    spatial: HashMap<String, Arc<SpatialControl>>,
+    effects: HashMap<String, Arc<EffectsControl>>,
+    breaths: HashMap<String, Breath>,
     fade_duration: Duration,
+    ramp_duration: Duration,
+    curve: RampCurve,
 }
 
 impl AudioEngine {
-    pub fn new() -> Result<Self> {
-        let available_hosts = cpal::available_hosts();
-        log::info!("Available audio hosts: {:?}", available_hosts);
-
-        let mut device = None;
-        let mut host_name = "Default";
-
-        let mut priority_hosts = Vec::new();
-
-        #[cfg(all(
-            any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"),
-            feature = "jack"
-        ))]
-        priority_hosts.push(HostId::Jack);
-
-        #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
-        priority_hosts.push(HostId::Alsa);
-
-        for &host_id in &priority_hosts {
-            if available_hosts.contains(&host_id) {
-                log::debug!("Attempting to use audio host: {:?}", host_id);
-                if let Ok(host) = cpal::host_from_id(host_id) {
-                    if let Some(d) = host.default_output_device() {
-                        log::info!(
-                            "Selected audio device from host {:?}: {}",
-                            host_id,
-                            d.name().unwrap_or_else(|_| "Unknown".to_string())
-                        );
-                        device = Some(d);
-                        host_name = match host_id {
-                            #[cfg(all(
-                                any(
-                                    target_os = "linux",
-                                    target_os = "dragonfly",
-                                    target_os = "freebsd"
-                                ),
-                                feature = "jack"
-                            ))]
-                            HostId::Jack => "JACK",
-                            #[cfg(any(
-                                target_os = "linux",
-                                target_os = "dragonfly",
-                                target_os = "freebsd"
-                            ))]
-                            HostId::Alsa => "ALSA",
-                            #[allow(unreachable_patterns)]
-                            _ => "Unknown",
-                        };
-
-                        break;
-                    }
-                }
-            }
-        }
-
-        let (_stream, stream_handle) = if let Some(d) = device {
-            OutputStream::try_from_device(&d)
-                .map_err(|e| anyhow::anyhow!("Failed to create output stream from device: {}", e))?
-        } else {
-            log::warn!("No preferred audio host found. Falling back to default.");
-            OutputStream::try_default().context("No audio output device available")?
+    /// Loudness measurements are cached in `loudness_cache`.
+    pub fn new(config: &AudioConfig, loudness_cache: &Path) -> Result<Self> {
+        let settings = StreamSettings {
+            sample_rate: config.sample_rate,
+            buffer_size: config.buffer_size,
         };
+        let stream = output::open(
+            &config.backend,
+            config.host.as_deref(),
+            config.device.as_deref(),
+            settings,
+        )?;
+        Ok(Self::with_output(config, loudness_cache, stream))
+    }
 
-        log::info!("Audio engine initialized successfully using {}", host_name);
-
-        Ok(Self {
-            _stream,
-            stream_handle,
+    /// An engine playing into an output that is already open.
+    pub fn with_output(
+        config: &AudioConfig,
+        loudness_cache: &Path,
+        stream: Box<dyn Output>,
+    ) -> Self {
+        let settings = StreamSettings {
+            sample_rate: config.sample_rate,
+            buffer_size: config.buffer_size,
+        };
+        let mixer = start_master_bus(stream.as_ref(), config.limiter);
+        let normalizer = config
+            .normalize_loudness
+            .then(|| Normalizer::new(config.loudness_target, loudness_cache));
+
+        Self {
+            stream,
+            backend: config.backend.clone(),
+            host: config.host.clone(),
+            device: config.device.clone(),
+            settings,
+            output_error: None,
+            reconnect: None,
+            mixer,
+            limiter: config.limiter,
+            normalizer,
+            cache: SampleCache::new(config.cache_mb),
             sinks: HashMap::new(),
             fading_sinks: Vec::new(),
             master_volume: 1.0,
+            master_ramp: None,
+            master_fade: None,
             sound_volumes: HashMap::new(),
+            volume_ramps: HashMap::new(),
+            spatial: HashMap::new(),
+            effects: HashMap::new(),
+            breaths: HashMap::new(),
+            playing: HashMap::new(),
             fade_duration: Duration::from_secs(2),
-        })
+            ramp_duration: Duration::from_millis(config.volume_ramp_ms),
+            curve: config.ramp_curve,
+        }
+    }
+
+    fn master_gain(&self) -> f32 {
+        let fade = self
+            .master_fade
+            .as_ref()
+            .map_or(1.0, |fade| fade.value(self.curve));
+        self.master_volume * fade
     }
 
-    pub fn update(&mut self, dt: Duration) {
-        let mut finished_indices = Vec::new();
+    // Builds the source for a sound and starts it fading in from silence
+    fn start_sink(&mut self, sound: &Sound, volume: f32) -> Result<()> {
+        let id = sound.id.as_str();
+        let spatial = SpatialControl::new(sound.spatial);
+        let effects = EffectsControl::new(sound.effects);
+        let source = sound_source(sound, spatial.clone(), effects.clone(), &self.cache)?;
+
+        log::debug!("Creating sink for: {}", id);
+
+        self.spatial.insert(id.to_string(), spatial);
+        self.effects.insert(id.to_string(), effects);
+
+        if let Some(normalizer) = &mut self.normalizer {
+            // Generated sounds are already level matched
+            if sound.generator.is_none() && sound.scatter.is_none() {
+                normalizer.track(id, &sound.file_path);
+            }
+        }
+
+        if sound.breathing.is_active() {
+            self.breaths
+                .insert(id.to_string(), Breath::new(sound.breathing));
+        } else {
+            self.breaths.remove(id);
+        }
+
+        let (sink, output) = Sink::new_idle();
+        self.mixer.add(output);
+        sink.append(source);
+
+        // Start silent and fade in along the configured curve
+        sink.set_volume(0.0);
+        self.sound_volumes.insert(id.to_string(), 0.0);
+        self.start_ramp(id, volume, self.fade_duration);
+
+        self.sinks.insert(id.to_string(), sink);
+        self.playing.insert(id.to_string(), sound.clone());
+        log::info!("Started playing '{}'", id);
+        Ok(())
+    }
+
+    /// Where a sound's volume is heading, which is where a rebuilt sink should end up.
+    fn target_volume(&self, id: &str) -> f32 {
+        match self.volume_ramps.get(id) {
+            Some(ramp) => ramp.to,
+            None => self.sound_volumes.get(id).copied().unwrap_or(0.0),
+        }
+    }
+
+    fn start_ramp(&mut self, id: &str, volume: f32, duration: Duration) {
+        let from = self.sound_volumes.get(id).copied().unwrap_or(volume);
+        self.volume_ramps
+            .insert(id.to_string(), VolumeRamp::new(from, volume, duration));
+    }
+
+    fn apply_master_gain(&self) {
+        let gain = self.master_gain();
+        for (id, sink) in &self.sinks {
+            if let Some(&vol) = self.sound_volumes.get(id) {
+                sink.set_volume(vol * gain * self.loudness_gain(id) * self.modulation(id));
+            }
+        }
+    }
+
+    /// Opens an output and moves every playing sound over to it.
+    fn reopen(&mut self, host: Option<String>, device: Option<String>) -> Result<()> {
+        // Open the new device first, so a failure leaves playback untouched
+        let stream = output::open(
+            &self.backend,
+            host.as_deref(),
+            device.as_deref(),
+            self.settings,
+        )?;
+        let mixer = start_master_bus(stream.as_ref(), self.limiter);
+
+        let sounds: Vec<(Sound, f32)> = self
+            .playing
+            .values()
+            .map(|sound| (sound.clone(), self.target_volume(&sound.id)))
+            .collect();
+        self.sinks.clear();
+        self.fading_sinks.clear();
+        self.volume_ramps.clear();
+        self.playing.clear();
+
+        self.stream = stream;
+        self.mixer = mixer;
+        self.host = host;
+        self.device = device;
+        self.output_error = None;
+        self.reconnect = None;
+
+        for (sound, volume) in sounds {
+            match self.start_sink(&sound, volume) {
+                // Come back quickly rather than with the slow play fade
+                Ok(()) => self.start_ramp(&sound.id, volume, self.ramp_duration),
+                Err(e) => log::error!("Failed to restart '{}' on the new device: {}", sound.id, e),
+            }
+        }
+        Ok(())
+    }
+
+    // Notices a dead stream and keeps trying to open the output again
+    fn watch_output(&mut self, dt: Duration) {
+        if self.reconnect.is_none() {
+            if let Some(error) = self.stream.failure(dt) {
+                log::error!("{}, reconnecting", error);
+                self.output_error = Some(format!("{}, reconnecting", error));
+                self.reconnect = Some(Backoff::new());
+            }
+        }
+
+        let Some(backoff) = &mut self.reconnect else {
+            return;
+        };
+        if !backoff.advance(dt) {
+            return;
+        }
+        match self.reopen(self.host.clone(), self.device.clone()) {
+            Ok(()) => log::info!("Audio output reconnected"),
+            Err(e) => {
+                if let Some(backoff) = &mut self.reconnect {
+                    backoff.failed();
+                    let secs = backoff.delay().as_secs();
+                    log::warn!("Reconnecting failed: {:#}, retrying in {}s", e, secs);
+                    self.output_error = Some(format!("{:#}, retrying in {}s", e, secs));
+                }
+            }
+        }
+    }
+
+    fn loudness_gain(&self, id: &str) -> f32 {
+        self.normalizer
+            .as_ref()
+            .map_or(1.0, |normalizer| normalizer.gain(id))
+    }
+}
+
+/// Everything a sound plays through before its sink: the file, loop, scatter
+/// or generator, then pan and width, then the filters.
+fn sound_source(
+    sound: &Sound,
+    spatial: Arc<SpatialControl>,
+    effects: Arc<EffectsControl>,
+    cache: &SampleCache,
+) -> Result<EffectsSource<SpatialSource>> {
+    let source: Box<dyn Source<Item = f32> + Send> = match (sound.generator, &sound.scatter) {
+        (Some(generator), _) => generators::source(generator, sound),
+        (None, Some(scatter)) => {
+            Box::new(scatter::ScatterSource::new(scatter.clone(), cache.clone())?)
+        }
+        (None, None) if sound.looping.is_plain() => cache.open_looping(&sound.file_path)?,
+        (None, None) => Box::new(looping::LoopSource::open(
+            &sound.file_path,
+            sound.looping,
+            cache.clone(),
+        )?),
+    };
+    Ok(EffectsSource::new(
+        SpatialSource::new(source, spatial),
+        effects,
+    ))
+}
 
-        for (i, fading) in self.fading_sinks.iter_mut().enumerate() {
-            fading.elapsed += dt;
-            if fading.elapsed >= fading.total_duration {
-                fading.sink.set_volume(0.0);
+// Every sink plays into one mixer, which reaches the device through the limiter
+fn start_master_bus(stream: &dyn Output, limiter: bool) -> Arc<DynamicMixerController<f32>> {
+    // Mixed in stereo at the device rate, so only the sounds get resampled
+    let (mixer, mix) = dynamic_mixer::mixer(2, stream.sample_rate());
+    stream.mixer().add(limiter::Limiter::new(mix, limiter));
+    mixer
+}
+
+impl Engine for AudioEngine {
+    fn update(&mut self, dt: Duration) {
+        self.watch_output(dt);
+        let curve = self.curve;
+
+        self.fading_sinks.retain_mut(|fading| {
+            fading.ramp.advance(dt);
+            if fading.ramp.is_done() {
                 fading.sink.stop();
-                finished_indices.push(i);
+                false
             } else {
-                let progress = fading.elapsed.as_secs_f32() / fading.total_duration.as_secs_f32();
-                let remaining_vol = fading.start_volume * (1.0 - progress);
-                fading.sink.set_volume(remaining_vol);
+                fading.sink.set_volume(fading.ramp.value(curve));
+                true
+            }
+        });
+
+        if self.normalizer.as_mut().is_some_and(Normalizer::poll) {
+            self.apply_master_gain();
+        }
+
+        for breath in self.breaths.values_mut() {
+            breath.advance(dt);
+        }
+        self.breaths.retain(|_, breath| !breath.is_settled());
+
+        let ramping = self.master_ramp.is_some()
+            || self.master_fade.is_some()
+            || !self.volume_ramps.is_empty()
+            || !self.breaths.is_empty();
+        if !ramping {
+            return;
+        }
+
+        if let Some(ramp) = &mut self.master_ramp {
+            ramp.advance(dt);
+            self.master_volume = ramp.value(curve);
+            if ramp.is_done() {
+                self.master_ramp = None;
             }
         }
 
-        for i in finished_indices.into_iter().rev() {
-            self.fading_sinks.swap_remove(i);
+        for (id, ramp) in &mut self.volume_ramps {
+            ramp.advance(dt);
+            self.sound_volumes.insert(id.clone(), ramp.value(curve));
         }
+        self.volume_ramps.retain(|_, ramp| !ramp.is_done());
+
+        let fade_finished = self.master_fade.as_mut().is_some_and(|fade| {
+            fade.advance(dt);
+            fade.is_done()
+        });
+        if fade_finished {
+            log::info!("Master fade finished, stopping all sounds");
+            self.stop_all();
+            self.master_fade = None;
+        }
+
+        self.apply_master_gain();
     }
 
-    pub fn play(&mut self, id: &str, file_path: &str, volume: f32) -> Result<()> {
-        log::info!("Attempting to play sound '{}' from '{}'", id, file_path);
+    fn play(&mut self, sound: &Sound, volume: f32) -> Result<()> {
+        let id = sound.id.as_str();
+        log::info!("Attempting to play sound '{}'", id);
         if self.sinks.contains_key(id) {
             log::debug!("Sound '{}' is already playing", id);
             return Ok(());
@@ -165,114 +488,247 @@ impl AudioEngine {
             fading.sink.stop();
         }
 
-        log::debug!("Opening file: {}", file_path);
-        let file =
-            File::open(file_path).context(format!("Failed to open sound file: {}", file_path))?;
+        // Decoded in the background, so later passes and plays come from memory
+        if sound.generator.is_none() {
+            match &sound.scatter {
+                Some(scatter) => scatter.files.iter().for_each(|file| self.cache.warm(file)),
+                None => self.cache.warm(&sound.file_path),
+            }
+        }
 
-        log::debug!("Creating decoder for: {}", file_path);
+        self.start_sink(sound, volume)
+    }
 
-        let file_for_closure = file.try_clone().context("Failed to clone file handle")?;
+    fn stop(&mut self, id: &str) {
+        self.fade_out(id, self.fade_duration);
+    }
 
-        let is_opus = file_path.to_lowercase().ends_with(".opus")
-            || file_path.to_lowercase().ends_with(".webm");
+    fn reload(&mut self, sound: &Sound) -> Result<()> {
+        let id = sound.id.as_str();
+        if !self.sinks.contains_key(id) {
+            return Ok(());
+        }
+        let volume = self.target_volume(id);
 
-        let source: Box<dyn Source<Item = f32> + Send> = if is_opus {
-            log::info!("Attempting to use Magnum (Opus) decoder for: {}", file_path);
-            match OpusSourceOgg::new(BufReader::new(file_for_closure)) {
-                Ok(decoder) => {
-                    log::info!("Magnum decoder created successfully.");
-                    Box::new(MagnumOggWrapper(decoder))
-                }
-                Err(e) => {
-                    log::error!("Magnum decoder failed: {:?}. Falling back to Rodio.", e);
-                    let file_fallback = file
-                        .try_clone()
-                        .context("Failed to clone file for fallback")?;
-                    let decoder_result =
-                        std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
-                            Decoder::new(BufReader::new(file_fallback))
-                        }));
-                    match decoder_result {
-                        Ok(Ok(d)) => Box::new(d.convert_samples()),
-                        Ok(Err(e)) => return Err(anyhow::anyhow!("Rodio decoder error: {}", e)),
-                        Err(_) => return Err(anyhow::anyhow!("Rodio decoder panicked.")),
-                    }
-                }
-            }
+        // The old sink fades out while the rebuilt one fades in
+        log::info!("Reloading sound '{}'", id);
+        self.fade_out(id, self.fade_duration);
+        self.start_sink(sound, volume)
+    }
+
+    fn set_volume(&mut self, id: &str, volume: f32) {
+        if self.sinks.contains_key(id) {
+            self.start_ramp(id, volume, self.ramp_duration);
         } else {
-            let decoder_result =
-                std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
-                    Decoder::new(BufReader::new(file_for_closure))
-                }));
-
-            match decoder_result {
-                Ok(result) => match result {
-                    Ok(d) => Box::new(d.convert_samples()),
-                    Err(e) => {
-                        log::error!("Failed to create decoder for '{}': {}", file_path, e);
-                        return Err(anyhow::anyhow!("Decoder error: {}", e));
-                    }
-                },
-                Err(_) => {
-                    log::error!("Decoder PANICKED for '{}'.", file_path);
-                    return Err(anyhow::anyhow!("Decoder panicked."));
-                }
+            self.sound_volumes.insert(id.to_string(), volume);
+        }
+    }
+
+    fn set_master_volume(&mut self, volume: f32) {
+        self.master_ramp = Some(VolumeRamp::new(
+            self.master_volume,
+            volume,
+            self.ramp_duration,
+        ));
+    }
+
+    fn set_spatial(&mut self, id: &str, spatial: Spatial) {
+        if let Some(control) = self.spatial.get(id) {
+            control.set(spatial);
+        }
+        if let Some(sound) = self.playing.get_mut(id) {
+            sound.spatial = spatial;
+        }
+    }
+
+    fn set_effects(&mut self, id: &str, effects: Effects) {
+        if let Some(control) = self.effects.get(id) {
+            control.set(effects);
+        }
+        if let Some(sound) = self.playing.get_mut(id) {
+            sound.effects = effects;
+        }
+    }
+
+    fn set_breathing(&mut self, id: &str, breathing: Breathing) {
+        if let Some(sound) = self.playing.get_mut(id) {
+            sound.breathing = breathing;
+        }
+        match self.breaths.get_mut(id) {
+            Some(breath) => breath.set(breathing),
+            None if breathing.is_active() && self.sinks.contains_key(id) => {
+                self.breaths.insert(id.to_string(), Breath::new(breathing));
             }
-        };
+            None => {}
+        }
+    }
 
-        let source = source.repeat_infinite().fade_in(self.fade_duration);
+    fn modulation(&self, id: &str) -> f32 {
+        self.breaths.get(id).map_or(1.0, Breath::value)
+    }
 
-        log::debug!("Creating sink for: {}", id);
+    fn switch_device(&mut self, host: Option<&str>, device: Option<&str>) -> Result<()> {
+        self.reopen(host.map(str::to_string), device.map(str::to_string))
+    }
 
-        let sink = Sink::try_new(&self.stream_handle)?;
-        sink.append(source);
+    fn output_error(&self) -> Option<&str> {
+        self.output_error.as_deref()
+    }
 
-        self.sound_volumes.insert(id.to_string(), volume);
-        let effective_vol = volume * self.master_volume;
-        sink.set_volume(effective_vol);
+    fn is_playing(&self, id: &str) -> bool {
+        self.sinks.contains_key(id)
+    }
 
-        self.sinks.insert(id.to_string(), sink);
-        log::info!("Started playing '{}'", id);
-        Ok(())
+    fn stop_all(&mut self) {
+        self.sinks.clear();
+        self.fading_sinks.clear();
+        self.volume_ramps.clear();
+        self.spatial.clear();
+        self.effects.clear();
+        self.breaths.clear();
+        self.playing.clear();
+    }
+
+    fn fade_out_master(&mut self, duration: Duration) {
+        self.master_fade = Some(VolumeRamp::new(1.0, 0.0, duration));
     }
 
-    pub fn stop(&mut self, id: &str) {
+    fn cancel_master_fade(&mut self) {
+        if self.master_fade.take().is_some() {
+            self.apply_master_gain();
+        }
+    }
+
+    fn is_fading_out(&self) -> bool {
+        self.master_fade.is_some()
+    }
+
+    fn fade_out(&mut self, id: &str, duration: Duration) {
+        self.volume_ramps.remove(id);
+        // The fading source keeps its own handles on these
+        self.spatial.remove(id);
+        self.effects.remove(id);
+        self.breaths.remove(id);
+        self.playing.remove(id);
         if let Some(sink) = self.sinks.remove(id) {
             let start_vol = sink.volume();
 
             self.fading_sinks.push(FadingSink {
                 id: id.to_string(),
                 sink,
-                start_volume: start_vol,
-                elapsed: Duration::ZERO,
-                total_duration: self.fade_duration,
+                ramp: VolumeRamp::new(start_vol, 0.0, duration),
             });
         }
     }
 
-    pub fn set_volume(&mut self, id: &str, volume: f32) {
-        self.sound_volumes.insert(id.to_string(), volume);
-        if let Some(sink) = self.sinks.get(id) {
-            let effective_vol = volume * self.master_volume;
-            sink.set_volume(effective_vol);
+    fn ramp_volume(&mut self, id: &str, volume: f32, duration: Duration) {
+        if self.sinks.contains_key(id) {
+            self.start_ramp(id, volume, duration);
         }
     }
+}
 
-    pub fn set_master_volume(&mut self, volume: f32) {
-        self.master_volume = volume;
-        for (id, sink) in &self.sinks {
-            if let Some(&vol) = self.sound_volumes.get(id) {
-                sink.set_volume(vol * self.master_volume);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::static_data::load_sounds_from_file;
+    use output::PullOutput;
+    use testing::{assert_level, write_hum, RATE};
+
+    struct Harness {
+        engine: AudioEngine,
+        output: PullOutput,
+        sound: Sound,
+        _dir: tempfile::TempDir,
+    }
+
+    impl Harness {
+        // A linear curve and no limiter, so levels can be worked out by hand
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::create_dir(dir.path().join("sounds")).unwrap();
+            // A steady 0.5, so the output level is the gain times 0.5
+            write_hum(&dir.path().join("sounds").join("hum.wav"), 0.5, 0.5);
+            let toml = dir.path().join("sounds.toml");
+            std::fs::write(&toml, "[Test.hum]\nfile = \"hum.wav\"\n").unwrap();
+            let sound = load_sounds_from_file(&toml).unwrap().remove(0);
+
+            let config = AudioConfig {
+                limiter: false,
+                ramp_curve: RampCurve::Linear,
+                volume_ramp_ms: 200,
+                ..Config::default().audio
+            };
+            let output = PullOutput::new(RATE);
+            let engine = AudioEngine::with_output(
+                &config,
+                &dir.path().join("loudness.toml"),
+                Box::new(output.clone()),
+            );
+            Self {
+                engine,
+                output,
+                sound,
+                _dir: dir,
             }
         }
+
+        // The left level at the end of every step
+        fn run(&mut self, duration: Duration) -> Vec<f32> {
+            let levels = testing::run(&mut self.engine, &self.output, duration);
+            levels.into_iter().map(|(left, _)| left).collect()
+        }
     }
 
-    pub fn is_playing(&self, id: &str) -> bool {
-        self.sinks.contains_key(id)
+    #[test]
+    fn sounds_fade_in_and_out() {
+        let mut h = Harness::new();
+        h.engine.play(&h.sound, 0.8).unwrap();
+        let levels = h.run(Duration::from_secs(3));
+        assert!(levels.windows(2).all(|pair| pair[0] <= pair[1]));
+        // Two seconds up to the slider
+        assert_level(levels[49], 0.2);
+        assert_level(levels[99], 0.4);
+        assert_level(levels[199], 0.8);
+        assert_level(levels[299], 0.8);
+
+        h.engine.stop("hum");
+        assert!(!h.engine.is_playing("hum"));
+        let levels = h.run(Duration::from_secs(3));
+        assert!(levels.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_level(levels[99], 0.4);
+        assert_level(levels[199], 0.0);
+        assert!(h.engine.fading_sinks.is_empty());
     }
 
-    pub fn stop_all(&mut self) {
-        self.sinks.clear();
-        self.fading_sinks.clear();
+    #[test]
+    fn volume_changes_ramp() {
+        let mut h = Harness::new();
+        h.engine.play(&h.sound, 0.8).unwrap();
+        h.run(Duration::from_secs(2));
+
+        h.engine.set_volume("hum", 0.2);
+        let levels = h.run(Duration::from_millis(300));
+        assert_level(levels[9], 0.5);
+        assert_level(levels[19], 0.2);
+        assert_level(levels[29], 0.2);
+    }
+
+    #[test]
+    fn master_volume_mutes_and_restores() {
+        let mut h = Harness::new();
+        h.engine.play(&h.sound, 0.8).unwrap();
+        h.run(Duration::from_secs(2));
+
+        h.engine.set_master_volume(0.0);
+        let levels = h.run(Duration::from_millis(300));
+        assert_level(levels[9], 0.4);
+        assert_level(levels[29], 0.0);
+        assert!(h.engine.is_playing("hum"));
+
+        h.engine.set_master_volume(1.0);
+        let levels = h.run(Duration::from_millis(300));
+        assert_level(levels[29], 0.8);
     }
 }
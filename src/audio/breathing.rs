@@ -0,0 +1,75 @@
+use super::generators::Rng;
+use crate::static_data::Breathing;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+/// Smooth value noise that wanders between `1.0 - depth` and 1.0: a new
+/// random level is picked every period or so and reached along a cosine, so
+/// the volume swells and ebbs without corners.
+pub struct Breath {
+    settings: Breathing,
+    rng: Rng,
+    from: f32,
+    to: f32,
+    // Progress from `from` to `to`, 0.0..1.0
+    progress: f32,
+    // Length of the current swell in seconds
+    length: f32,
+}
+
+impl Breath {
+    pub fn new(settings: Breathing) -> Self {
+        let mut breath = Self {
+            settings,
+            rng: Rng::seeded(),
+            // Sounds start at their slider level and drift from there
+            from: 1.0,
+            to: 1.0,
+            progress: 1.0,
+            length: settings.period,
+        };
+        breath.next_target();
+        breath
+    }
+
+    pub fn set(&mut self, settings: Breathing) {
+        self.settings = settings;
+        // Leave the current level where it is and head for a target in range
+        self.from = self.value();
+        self.next_target();
+    }
+
+    fn random(&mut self) -> f32 {
+        (self.rng.next_f32() + 1.0) / 2.0
+    }
+
+    fn next_target(&mut self) {
+        self.to = 1.0 - self.settings.depth * self.random();
+        self.progress = 0.0;
+        // Vary the pace too, so the swells don't fall into a rhythm
+        self.length = self.settings.period * (0.5 + self.random());
+    }
+
+    pub fn advance(&mut self, dt: Duration) {
+        self.progress += dt.as_secs_f32() / self.length.max(1.0);
+        if self.progress >= 1.0 {
+            self.from = self.to;
+            self.next_target();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.settings.is_active()
+    }
+
+    /// Whether a switched off breath has drifted back to the slider level.
+    pub fn is_settled(&self) -> bool {
+        !self.is_active() && self.from >= 1.0
+    }
+
+    /// The factor applied to the sound's volume right now.
+    pub fn value(&self) -> f32 {
+        let t = (1.0 - (self.progress.min(1.0) * PI).cos()) / 2.0;
+        self.from + (self.to - self.from) * t
+    }
+}
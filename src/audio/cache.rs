@@ -0,0 +1,278 @@
+use super::decoder;
+use anyhow::Result;
+use rodio::source::SeekError;
+use rodio::Source;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::time::Duration;
+
+type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+/// A file decoded into memory.
+pub struct Samples {
+    data: Box<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Samples {
+    fn bytes(&self) -> usize {
+        std::mem::size_of_val(&*self.data)
+    }
+}
+
+struct Entry {
+    samples: Arc<Samples>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    budget: usize,
+    used: usize,
+    entries: HashMap<String, Entry>,
+    // Bumped on every lookup, the entry with the oldest value goes first
+    clock: u64,
+    pending: HashSet<String>,
+    // Files too large for the budget or that failed to decode
+    skipped: HashSet<String>,
+}
+
+impl Inner {
+    fn insert(&mut self, path: String, samples: Samples) {
+        let bytes = samples.bytes();
+        while self.used + bytes > self.budget {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            log::debug!("Dropping '{}' from the sample cache", oldest);
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.used -= entry.samples.bytes();
+            }
+        }
+        self.used += bytes;
+        self.clock += 1;
+        let entry = Entry {
+            samples: Arc::new(samples),
+            last_used: self.clock,
+        };
+        self.entries.insert(path, entry);
+    }
+}
+
+/// Decoded files kept in memory up to a budget, so loops are decoded once
+/// and sounds played again start without touching the disk.
+///
+/// Files are decoded in the background once `warm` asks for them, and until
+/// then stream from disk as usual. Lookups never wait on the lock, so sources
+/// may open files from the audio thread. Clones share the same cache.
+#[derive(Clone, Default)]
+pub struct SampleCache(Option<Arc<Mutex<Inner>>>);
+
+impl SampleCache {
+    /// A cache holding up to `budget_mb` megabytes, or none at all for 0.
+    pub fn new(budget_mb: u32) -> Self {
+        if budget_mb == 0 {
+            return Self(None);
+        }
+        Self(Some(Arc::new(Mutex::new(Inner {
+            budget: budget_mb as usize * 1024 * 1024,
+            ..Inner::default()
+        }))))
+    }
+
+    /// Plays a file once, from memory if it is cached.
+    pub fn open(&self, path: &str) -> Result<BoxedSource> {
+        match self.get(path) {
+            Some(samples) => Ok(Box::new(CachedSource::new(samples, false))),
+            None => decoder::open(path),
+        }
+    }
+
+    /// Plays a file over and over, from memory if it is cached.
+    pub fn open_looping(&self, path: &str) -> Result<BoxedSource> {
+        match self.get(path) {
+            Some(samples) => Ok(Box::new(CachedSource::new(samples, true))),
+            None => Ok(Box::new(decoder::open(path)?.repeat_infinite())),
+        }
+    }
+
+    // Looks a file up, giving up rather than waiting if the cache is busy
+    fn get(&self, path: &str) -> Option<Arc<Samples>> {
+        let mut inner = match self.0.as_ref()?.try_lock() {
+            Ok(inner) => inner,
+            Err(TryLockError::WouldBlock) => return None,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        };
+        inner.clock += 1;
+        let clock = inner.clock;
+        let entry = inner.entries.get_mut(path)?;
+        entry.last_used = clock;
+        Some(entry.samples.clone())
+    }
+
+    /// Starts decoding a file in the background if it isn't cached yet.
+    pub fn warm(&self, path: &str) {
+        let Some(shared) = self.0.as_ref() else {
+            return;
+        };
+        let mut inner = shared.lock().unwrap_or_else(PoisonError::into_inner);
+        if inner.entries.contains_key(path)
+            || inner.pending.contains(path)
+            || inner.skipped.contains(path)
+        {
+            return;
+        }
+
+        inner.pending.insert(path.to_string());
+        let budget = inner.budget;
+        let shared = shared.clone();
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            let result = decode(&path, budget);
+            let mut inner = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            inner.pending.remove(&path);
+            match result {
+                Ok(Some(samples)) => {
+                    log::info!(
+                        "Cached '{}' ({:.1} MB)",
+                        path,
+                        samples.bytes() as f64 / 1024.0 / 1024.0
+                    );
+                    inner.insert(path, samples);
+                }
+                Ok(None) => {
+                    log::info!("'{}' is too large for the sample cache", path);
+                    inner.skipped.insert(path);
+                }
+                Err(e) => {
+                    log::warn!("Could not cache '{}': {}", path, e);
+                    inner.skipped.insert(path);
+                }
+            }
+        });
+    }
+}
+
+// Decodes a whole file, giving up once it outgrows `budget` bytes
+fn decode(path: &str, budget: usize) -> Result<Option<Samples>> {
+    let source = decoder::open(path)?;
+    let channels = source.channels();
+    let sample_rate = source.sample_rate();
+    let limit = budget / std::mem::size_of::<f32>();
+    let fits = |duration: Duration| {
+        duration.as_secs_f64() * sample_rate as f64 * channels as f64 <= limit as f64
+    };
+    if source
+        .total_duration()
+        .is_some_and(|duration| !fits(duration))
+    {
+        return Ok(None);
+    }
+
+    let data: Vec<f32> = source.take(limit + 1).collect();
+    if data.len() > limit {
+        return Ok(None);
+    }
+    Ok(Some(Samples {
+        data: data.into_boxed_slice(),
+        channels,
+        sample_rate,
+    }))
+}
+
+/// Plays a cached file from memory.
+pub struct CachedSource {
+    samples: Arc<Samples>,
+    position: usize,
+    looping: bool,
+}
+
+impl CachedSource {
+    fn new(samples: Arc<Samples>, looping: bool) -> Self {
+        Self {
+            samples,
+            position: 0,
+            looping,
+        }
+    }
+}
+
+impl Iterator for CachedSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.samples.data.len() {
+            if !self.looping || self.position == 0 {
+                return None;
+            }
+            self.position = 0;
+        }
+        self.position += 1;
+        Some(self.samples.data[self.position - 1])
+    }
+}
+
+impl Source for CachedSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.samples.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.samples.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.samples.data.len() / self.samples.channels.max(1) as usize;
+        (!self.looping)
+            .then(|| Duration::from_secs_f64(frames as f64 / self.samples.sample_rate as f64))
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let channels = self.samples.channels.max(1) as usize;
+        let frame = (pos.as_secs_f64() * self.samples.sample_rate as f64) as usize;
+        self.position = (frame * channels).min(self.samples.data.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::testing::write_wav;
+
+    #[test]
+    fn plays_from_memory_once_warmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.wav");
+        write_wav(&path, 1, 8000, 0..800);
+        let path = path.to_str().unwrap();
+
+        let cache = SampleCache::new(1);
+        // Nothing is decoded until asked for, the file streams instead
+        assert!(cache.get(path).is_none());
+        assert_eq!(cache.open(path).unwrap().count(), 800);
+
+        cache.warm(path);
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while cache.get(path).is_none() {
+            assert!(std::time::Instant::now() < deadline, "never cached");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(cache.open(path).unwrap().count(), 800);
+
+        // A lookup while the cache is busy misses instead of waiting
+        let shared = cache.0.as_ref().unwrap();
+        let _busy = shared.lock().unwrap();
+        assert!(cache.get(path).is_none());
+    }
+}
@@ -0,0 +1,106 @@
+#[cfg(feature = "opus")]
+use super::opus::OpusSource;
+#[cfg(feature = "opus")]
+use super::webm::WebmPackets;
+use anyhow::{Context, Result};
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+// Enough of the start of a file to hold the first Ogg page's header packet
+const SNIFF_LEN: usize = 64;
+
+/// A file format recognised by the bytes it starts with.
+struct Format {
+    name: &'static str,
+    sniff: fn(&[u8]) -> bool,
+    open: fn(BufReader<File>) -> Result<BoxedSource>,
+}
+
+const FORMATS: &[Format] = &[
+    #[cfg(feature = "opus")]
+    Format {
+        name: "Ogg Opus",
+        sniff: |head| head.starts_with(b"OggS") && contains(head, b"OpusHead"),
+        open: |reader| Ok(Box::new(OpusSource::ogg(reader)?)),
+    },
+    Format {
+        name: "Ogg Vorbis",
+        sniff: |head| head.starts_with(b"OggS") && contains(head, b"\x01vorbis"),
+        open: open_rodio,
+    },
+    #[cfg(feature = "opus")]
+    Format {
+        name: "WebM",
+        sniff: |head| head.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]),
+        open: |reader| {
+            let (head, packets) = WebmPackets::open(reader)?;
+            Ok(Box::new(OpusSource::new(&head, Box::new(packets))?))
+        },
+    },
+    Format {
+        name: "WAV",
+        sniff: |head| head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WAVE"),
+        open: open_rodio,
+    },
+    Format {
+        name: "FLAC",
+        sniff: |head| head.starts_with(b"fLaC"),
+        open: open_rodio,
+    },
+    Format {
+        name: "MP3",
+        // An ID3 tag, or straight into an MPEG frame sync
+        sniff: |head| {
+            head.starts_with(b"ID3")
+                || (head.len() >= 2 && head[0] == 0xff && head[1] & 0xe0 == 0xe0)
+        },
+        open: open_rodio,
+    },
+];
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// Opens a sound file with whichever decoder handles it, going by the
+/// file's first bytes rather than its extension.
+pub fn open(file_path: &str) -> Result<BoxedSource> {
+    log::debug!("Opening file: {}", file_path);
+    let file =
+        File::open(file_path).context(format!("Failed to open sound file: {}", file_path))?;
+    let mut reader = BufReader::new(file);
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    reader
+        .by_ref()
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .context(format!("Failed to read sound file: {}", file_path))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    let Some(format) = FORMATS.iter().find(|format| (format.sniff)(&head)) else {
+        // Rodio probes a few more containers itself
+        log::debug!("Unrecognised format, trying Rodio for: {}", file_path);
+        return open_rodio(reader).with_context(|| format!("Can't play '{}'", file_path));
+    };
+    log::debug!("Decoding '{}' as {}", file_path, format.name);
+    (format.open)(reader).with_context(|| format!("Can't play '{}' ({})", file_path, format.name))
+}
+
+fn open_rodio(reader: BufReader<File>) -> Result<BoxedSource> {
+    let decoder_result =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || Decoder::new(reader)));
+
+    match decoder_result {
+        Ok(result) => match result {
+            Ok(d) => Ok(Box::new(d.convert_samples())),
+            Err(e) => Err(anyhow::anyhow!("Decoder error: {}", e)),
+        },
+        Err(_) => Err(anyhow::anyhow!("Decoder panicked.")),
+    }
+}
@@ -0,0 +1,359 @@
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BufferSize, Device, FromSample, HostId, SampleFormat, SampleRate, SizedSample, StreamConfig,
+    SupportedBufferSize, SupportedStreamConfigRange,
+};
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+// A stream that hasn't asked for audio in this long has died quietly
+const STALL_TIMEOUT: Duration = Duration::from_secs(3);
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// An output device as named in `audio.host` and `audio.device`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputDevice {
+    pub host: String,
+    pub name: String,
+}
+
+/// Every output device of every host cpal can reach on this machine.
+pub fn list_output_devices() -> Vec<OutputDevice> {
+    let mut devices = Vec::new();
+    for host_id in cpal::available_hosts() {
+        let Ok(host) = cpal::host_from_id(host_id) else {
+            continue;
+        };
+        let Ok(outputs) = host.output_devices() else {
+            log::warn!("Could not list output devices of {}", host_id.name());
+            continue;
+        };
+        for device in outputs {
+            if let Ok(name) = device.name() {
+                devices.push(OutputDevice {
+                    host: host_id.name().to_string(),
+                    name,
+                });
+            }
+        }
+    }
+    devices
+}
+
+/// Sample rate and buffer size asked of the device, its own defaults where unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StreamSettings {
+    pub sample_rate: Option<u32>,
+    pub buffer_size: Option<u32>,
+}
+
+/// A running cpal stream and the mixer it plays. Playback stops when dropped.
+pub struct OutputStream {
+    _stream: cpal::Stream,
+    pub mixer: Arc<DynamicMixerController<f32>>,
+    pub sample_rate: u32,
+    health: Arc<Health>,
+    last_callbacks: u64,
+    stalled_for: Duration,
+}
+
+// Written from the audio thread, read on every engine update
+#[derive(Default)]
+struct Health {
+    callbacks: AtomicU64,
+    error: Mutex<Option<String>>,
+}
+
+impl OutputStream {
+    /// Why the stream has stopped playing, if it has: the device went away,
+    /// or the backend stopped asking for audio without saying so.
+    pub fn failure(&mut self, dt: Duration) -> Option<String> {
+        if let Some(error) = self
+            .health
+            .error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+        {
+            return Some(error);
+        }
+        let callbacks = self.health.callbacks.load(Ordering::Relaxed);
+        if callbacks != self.last_callbacks {
+            self.last_callbacks = callbacks;
+            self.stalled_for = Duration::ZERO;
+            return None;
+        }
+        self.stalled_for += dt;
+        (self.stalled_for >= STALL_TIMEOUT).then(|| "Audio output stopped responding".to_string())
+    }
+}
+
+/// Time to wait between attempts to open an output, doubling after every
+/// failure so a missing device isn't hammered.
+pub struct Backoff {
+    delay: Duration,
+    wait: Duration,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self {
+            delay: MIN_RETRY_DELAY,
+            wait: MIN_RETRY_DELAY,
+        }
+    }
+
+    /// Counts down, returns whether the next attempt is due.
+    pub fn advance(&mut self, dt: Duration) -> bool {
+        self.wait = self.wait.saturating_sub(dt);
+        self.wait.is_zero()
+    }
+
+    pub fn failed(&mut self) {
+        self.delay = (self.delay * 2).min(MAX_RETRY_DELAY);
+        self.wait = self.delay;
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+/// Opens the configured host and device. Without a device the host's
+/// default is used, and without either the automatic choice.
+pub fn open_stream(
+    host: Option<&str>,
+    device: Option<&str>,
+    settings: StreamSettings,
+) -> Result<OutputStream> {
+    let found = match (host, device) {
+        (None, None) => find_automatic()?,
+        _ => find_device(host, device)?,
+    };
+    let name = found.name().unwrap_or_else(|_| "Unknown".to_string());
+    let (config, format) = stream_config(&found, &name, settings)?;
+    log::info!(
+        "Opening '{}' at {} Hz, {} channels, {:?} buffer",
+        name,
+        config.sample_rate.0,
+        config.channels,
+        config.buffer_size
+    );
+    build_stream(&found, config, format)
+        .with_context(|| format!("Failed to open an output stream on '{}'", name))
+}
+
+fn find_device(host: Option<&str>, device: Option<&str>) -> Result<Device> {
+    let hosts: Vec<HostId> = match host {
+        Some(name) => {
+            let id = cpal::available_hosts()
+                .into_iter()
+                .find(|id| id.name().eq_ignore_ascii_case(name))
+                .with_context(|| format!("Audio host '{}' is not available", name))?;
+            vec![id]
+        }
+        None => cpal::available_hosts(),
+    };
+
+    for host_id in hosts {
+        let host = cpal::host_from_id(host_id)?;
+        let found = match device {
+            Some(name) => host
+                .output_devices()?
+                .find(|d| d.name().is_ok_and(|n| n == name)),
+            None => host.default_output_device(),
+        };
+        if let Some(found) = found {
+            log::info!("Using audio host {}", host_id.name());
+            return Ok(found);
+        }
+    }
+
+    match device {
+        Some(name) => anyhow::bail!("Audio device '{}' not found", name),
+        None => anyhow::bail!("Audio host has no default output device"),
+    }
+}
+
+// JACK, then ALSA, then whatever cpal considers the default
+fn find_automatic() -> Result<Device> {
+    let available_hosts = cpal::available_hosts();
+    log::info!("Available audio hosts: {:?}", available_hosts);
+
+    let mut priority_hosts = Vec::new();
+
+    #[cfg(all(
+        any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"),
+        feature = "jack"
+    ))]
+    priority_hosts.push(HostId::Jack);
+
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd"))]
+    priority_hosts.push(HostId::Alsa);
+
+    for &host_id in &priority_hosts {
+        if available_hosts.contains(&host_id) {
+            log::debug!("Attempting to use audio host: {:?}", host_id);
+            if let Ok(host) = cpal::host_from_id(host_id) {
+                if let Some(d) = host.default_output_device() {
+                    log::info!("Selected audio device from host {:?}", host_id);
+                    return Ok(d);
+                }
+            }
+        }
+    }
+
+    log::warn!("No preferred audio host found. Falling back to default.");
+    cpal::default_host()
+        .default_output_device()
+        .context("No audio output device available")
+}
+
+/// Checks the requested rate and buffer size against what the device
+/// supports, so a bad value is reported instead of failing somewhere in cpal.
+fn stream_config(
+    device: &Device,
+    name: &str,
+    settings: StreamSettings,
+) -> Result<(StreamConfig, SampleFormat)> {
+    let default = device
+        .default_output_config()
+        .with_context(|| format!("'{}' has no usable output configuration", name))?;
+    let supported: Vec<SupportedStreamConfigRange> = device
+        .supported_output_configs()
+        .map(|configs| configs.collect())
+        .unwrap_or_default();
+
+    let config = match settings.sample_rate {
+        None => default,
+        Some(rate) => {
+            let fits = |c: &&SupportedStreamConfigRange| {
+                c.min_sample_rate().0 <= rate && rate <= c.max_sample_rate().0
+            };
+            // Keep the default channel count and format where the rate allows
+            let range = supported
+                .iter()
+                .filter(fits)
+                .find(|c| {
+                    c.channels() == default.channels()
+                        && c.sample_format() == default.sample_format()
+                })
+                .or_else(|| supported.iter().find(fits));
+            match range {
+                Some(range) => range.with_sample_rate(SampleRate(rate)),
+                None => anyhow::bail!(
+                    "'{}' does not support a sample rate of {} Hz (supported: {})",
+                    name,
+                    rate,
+                    describe_rates(&supported)
+                ),
+            }
+        }
+    };
+
+    let buffer_size = match (settings.buffer_size, config.buffer_size()) {
+        (None, _) => BufferSize::Default,
+        (Some(frames), SupportedBufferSize::Range { min, max })
+            if frames < *min || frames > *max =>
+        {
+            anyhow::bail!(
+                "'{}' does not support a buffer of {} frames (supported: {}-{})",
+                name,
+                frames,
+                min,
+                max
+            )
+        }
+        (Some(frames), _) => BufferSize::Fixed(frames),
+    };
+
+    let stream_config = StreamConfig {
+        channels: config.channels(),
+        sample_rate: config.sample_rate(),
+        buffer_size,
+    };
+    Ok((stream_config, config.sample_format()))
+}
+
+fn describe_rates(supported: &[SupportedStreamConfigRange]) -> String {
+    let mut ranges: Vec<(u32, u32)> = supported
+        .iter()
+        .map(|c| (c.min_sample_rate().0, c.max_sample_rate().0))
+        .collect();
+    ranges.sort_unstable();
+    ranges.dedup();
+    if ranges.is_empty() {
+        return "unknown".to_string();
+    }
+    ranges
+        .iter()
+        .map(|&(min, max)| {
+            if min == max {
+                format!("{} Hz", min)
+            } else {
+                format!("{}-{} Hz", min, max)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn build_stream(
+    device: &Device,
+    config: StreamConfig,
+    format: SampleFormat,
+) -> Result<OutputStream> {
+    let (mixer, output) = dynamic_mixer::mixer::<f32>(config.channels, config.sample_rate.0);
+    let health = Arc::new(Health::default());
+    let h = health.clone();
+    let stream = match format {
+        SampleFormat::I8 => build::<i8>(device, &config, output, h),
+        SampleFormat::I16 => build::<i16>(device, &config, output, h),
+        SampleFormat::I32 => build::<i32>(device, &config, output, h),
+        SampleFormat::U8 => build::<u8>(device, &config, output, h),
+        SampleFormat::U16 => build::<u16>(device, &config, output, h),
+        SampleFormat::U32 => build::<u32>(device, &config, output, h),
+        SampleFormat::F64 => build::<f64>(device, &config, output, h),
+        _ => build::<f32>(device, &config, output, h),
+    }?;
+    stream.play()?;
+    Ok(OutputStream {
+        _stream: stream,
+        mixer,
+        sample_rate: config.sample_rate.0,
+        health,
+        last_callbacks: 0,
+        stalled_for: Duration::ZERO,
+    })
+}
+
+fn build<T: SizedSample + FromSample<f32>>(
+    device: &Device,
+    config: &StreamConfig,
+    mut output: DynamicMixer<f32>,
+    health: Arc<Health>,
+) -> Result<cpal::Stream> {
+    let callbacks = health.clone();
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            for sample in data.iter_mut() {
+                *sample = T::from_sample(output.next().unwrap_or(0.0));
+            }
+            callbacks.callbacks.fetch_add(1, Ordering::Relaxed);
+        },
+        move |e| {
+            log::error!("Audio stream error: {}", e);
+            // Other errors are often passing, a dead stream shows up as a stall
+            if matches!(e, cpal::StreamError::DeviceNotAvailable) {
+                *health.error.lock().unwrap_or_else(PoisonError::into_inner) = Some(e.to_string());
+            }
+        },
+        None,
+    )?;
+    Ok(stream)
+}
@@ -0,0 +1,341 @@
+use crate::static_data::Effects;
+use rodio::Source;
+use std::f32::consts::{PI, SQRT_2};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::time::Duration;
+
+const CHANNELS: usize = 2;
+// Corner frequencies of the three EQ bands
+const LOW_SHELF_HZ: f32 = 250.0;
+const MID_PEAK_HZ: f32 = 1000.0;
+const HIGH_SHELF_HZ: f32 = 4000.0;
+const MID_Q: f32 = 0.7;
+const FILTER_Q: f32 = 1.0 / SQRT_2;
+
+/// Filter settings of a playing sound, shared with its audio thread.
+#[derive(Debug)]
+pub struct EffectsControl {
+    effects: Mutex<Effects>,
+    // Bumped on every change so the audio thread only looks when it must
+    generation: AtomicU32,
+}
+
+impl EffectsControl {
+    pub fn new(effects: Effects) -> Arc<Self> {
+        Arc::new(Self {
+            effects: Mutex::new(effects),
+            generation: AtomicU32::new(0),
+        })
+    }
+
+    pub fn set(&self, effects: Effects) {
+        if let Ok(mut current) = self.effects.lock() {
+            *current = effects;
+        }
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+}
+
+// Coefficients from the RBJ audio EQ cookbook, normalized by a0
+#[derive(Clone, Copy)]
+pub(super) struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Coefficients {
+    fn new(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    fn low_pass(frequency: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::angle(frequency, sample_rate, FILTER_Q);
+        let b = (1.0 - cos) / 2.0;
+        Self::new(b, 2.0 * b, b, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    pub(super) fn high_pass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = Self::angle(frequency, sample_rate, q);
+        let b = (1.0 + cos) / 2.0;
+        Self::new(b, -2.0 * b, b, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    fn peak(frequency: f32, gain_db: f32, sample_rate: u32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::angle(frequency, sample_rate, MID_Q);
+        Self::new(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        )
+    }
+
+    pub(super) fn shelf(frequency: f32, gain_db: f32, sample_rate: u32, high: bool) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        // A shelf slope of 1 gives the same alpha as Q = 1/sqrt(2)
+        let (cos, alpha) = Self::angle(frequency, sample_rate, FILTER_Q);
+        let root = 2.0 * a.sqrt() * alpha;
+        // The high shelf is the low shelf with the sign of cos flipped
+        let cos = if high { -cos } else { cos };
+        let sign = if high { -1.0 } else { 1.0 };
+        Self::new(
+            a * ((a + 1.0) - (a - 1.0) * cos + root),
+            sign * 2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - root),
+            (a + 1.0) + (a - 1.0) * cos + root,
+            sign * -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - root,
+        )
+    }
+
+    fn angle(frequency: f32, sample_rate: u32, q: f32) -> (f32, f32) {
+        // Keep the corner below Nyquist whatever the file's rate
+        let frequency = frequency.min(sample_rate as f32 * 0.45);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+}
+
+// One biquad with separate state for each channel
+pub(super) struct Biquad {
+    coefficients: Coefficients,
+    state: [[f32; 4]; CHANNELS],
+}
+
+impl Biquad {
+    pub(super) fn new(coefficients: Coefficients) -> Self {
+        Self {
+            coefficients,
+            state: [[0.0; 4]; CHANNELS],
+        }
+    }
+
+    pub(super) fn process(&mut self, channel: usize, x: f32) -> f32 {
+        let c = self.coefficients;
+        let [x1, x2, y1, y2] = self.state[channel];
+        let y = c.b0 * x + c.b1 * x1 + c.b2 * x2 - c.a1 * y1 - c.a2 * y2;
+        self.state[channel] = [x, x1, y, y1];
+        y
+    }
+}
+
+// Which filter a stage is, so its memory follows it when others come and go
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    HighPass,
+    LowPass,
+    LowShelf,
+    MidPeak,
+    HighShelf,
+}
+
+/// Runs a stereo source through the low-pass, high-pass and three EQ bands.
+/// Stages at their neutral setting are left out.
+pub struct EffectsSource<S> {
+    input: S,
+    control: Arc<EffectsControl>,
+    generation: u32,
+    sample_rate: u32,
+    stages: Vec<(Stage, Biquad)>,
+    channel: usize,
+}
+
+impl<S: Source<Item = f32>> EffectsSource<S> {
+    pub fn new(input: S, control: Arc<EffectsControl>) -> Self {
+        let sample_rate = input.sample_rate();
+        let generation = control.generation.load(Ordering::Acquire);
+        let effects = *control
+            .effects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut source = Self {
+            input,
+            control,
+            generation,
+            sample_rate,
+            stages: Vec::new(),
+            channel: 0,
+        };
+        source.rebuild(effects);
+        source
+    }
+
+    // Picks up new settings, called from the audio thread so a busy lock is
+    // tried again on the next frame instead of waited for
+    fn refresh(&mut self) {
+        let generation = self.control.generation.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        let effects = match self.control.effects.try_lock() {
+            Ok(effects) => *effects,
+            Err(TryLockError::Poisoned(poisoned)) => *poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        self.generation = generation;
+        self.rebuild(effects);
+    }
+
+    fn rebuild(&mut self, effects: Effects) {
+        let rate = self.sample_rate;
+        let mut coefficients = Vec::new();
+        if effects.high_pass > Effects::HIGH_PASS_OFF {
+            let c = Coefficients::high_pass(effects.high_pass, FILTER_Q, rate);
+            coefficients.push((Stage::HighPass, c));
+        }
+        if effects.low_pass < Effects::LOW_PASS_OFF {
+            let c = Coefficients::low_pass(effects.low_pass, rate);
+            coefficients.push((Stage::LowPass, c));
+        }
+        if effects.low_db != 0.0 {
+            let c = Coefficients::shelf(LOW_SHELF_HZ, effects.low_db, rate, false);
+            coefficients.push((Stage::LowShelf, c));
+        }
+        if effects.mid_db != 0.0 {
+            let c = Coefficients::peak(MID_PEAK_HZ, effects.mid_db, rate);
+            coefficients.push((Stage::MidPeak, c));
+        }
+        if effects.high_db != 0.0 {
+            let c = Coefficients::shelf(HIGH_SHELF_HZ, effects.high_db, rate, true);
+            coefficients.push((Stage::HighShelf, c));
+        }
+
+        // Keep the filter memory of stages that survive, so tweaks don't
+        // click, and start the ones switched on from silence
+        let mut old = std::mem::take(&mut self.stages);
+        self.stages = coefficients
+            .into_iter()
+            .map(|(kind, c)| match old.iter().position(|(k, _)| *k == kind) {
+                Some(pos) => {
+                    let (_, mut stage) = old.swap_remove(pos);
+                    stage.coefficients = c;
+                    (kind, stage)
+                }
+                None => (kind, Biquad::new(c)),
+            })
+            .collect();
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for EffectsSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channel = self.channel;
+        if channel == 0 {
+            self.refresh();
+        }
+        self.channel = (self.channel + 1) % CHANNELS;
+
+        let mut sample = self.input.next()?;
+        for (_, stage) in &mut self.stages {
+            sample = stage.process(channel, sample);
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for EffectsSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        CHANNELS as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    const RATE: u32 = 48000;
+
+    fn signal(frames: usize) -> Vec<f32> {
+        (0..frames * CHANNELS)
+            .map(|i| ((i * 7919) % 2000) as f32 / 1000.0 - 1.0)
+            .collect()
+    }
+
+    #[test]
+    fn stages_keep_their_own_memory() {
+        let input = signal(2000);
+        let shelf = Effects {
+            low_db: 6.0,
+            ..Effects::default()
+        };
+        let control = EffectsControl::new(shelf);
+        let mut source = EffectsSource::new(
+            SamplesBuffer::new(CHANNELS as u16, RATE, input.clone()),
+            control.clone(),
+        );
+        let mut played: Vec<f32> = source.by_ref().take(1000 * CHANNELS).collect();
+        // The high-pass goes in front of the shelf
+        control.set(Effects {
+            high_pass: 200.0,
+            ..shelf
+        });
+        played.extend(source);
+
+        let mut low_shelf = Biquad::new(Coefficients::shelf(LOW_SHELF_HZ, 6.0, RATE, false));
+        let mut high_pass = Biquad::new(Coefficients::high_pass(200.0, FILTER_Q, RATE));
+        let expected: Vec<f32> = input
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                let channel = i % CHANNELS;
+                let x = if i < 1000 * CHANNELS {
+                    x
+                } else {
+                    high_pass.process(channel, x)
+                };
+                low_shelf.process(channel, x)
+            })
+            .collect();
+        assert_eq!(played, expected);
+    }
+
+    #[test]
+    fn busy_settings_wait_for_the_next_frame() {
+        let control = EffectsControl::new(Effects::default());
+        let input = SamplesBuffer::new(CHANNELS as u16, RATE, signal(10));
+        let mut source = EffectsSource::new(input, control.clone());
+
+        let guard = control.effects.lock().unwrap();
+        control.generation.fetch_add(1, Ordering::Release);
+        source.next();
+        source.next();
+        assert_eq!(source.generation, 0);
+        drop(guard);
+
+        control.set(Effects {
+            mid_db: 3.0,
+            ..Effects::default()
+        });
+        source.next();
+        assert_eq!(source.generation, 2);
+        assert_eq!(source.stages.len(), 1);
+    }
+}
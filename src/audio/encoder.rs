@@ -0,0 +1,69 @@
+#[cfg(feature = "opus")]
+use super::opus::OpusEncoder;
+use anyhow::Result;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Takes interleaved stereo samples and writes them to a file.
+pub trait Encoder: Send {
+    fn write(&mut self, samples: &[f32]) -> Result<()>;
+    /// Flushes what is buffered and completes the file's headers.
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Picks the encoder from the file extension: `.wav` or `.opus`/`.ogg`.
+pub fn create(path: &Path, sample_rate: u32) -> Result<Box<dyn Encoder>> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let encoder: Box<dyn Encoder> = match extension.as_str() {
+        "wav" => Box::new(WavEncoder::create(path, sample_rate)?),
+        #[cfg(feature = "opus")]
+        "opus" | "ogg" => Box::new(OpusEncoder::create(path, sample_rate)?),
+        #[cfg(not(feature = "opus"))]
+        "opus" | "ogg" => anyhow::bail!("This build of tanin has no Opus support"),
+        _ => anyhow::bail!(
+            "Can't tell the format of '{}', use a .wav or .opus file",
+            path.display()
+        ),
+    };
+    Ok(encoder)
+}
+
+pub fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+struct WavEncoder {
+    writer: hound::WavWriter<BufWriter<File>>,
+}
+
+impl WavEncoder {
+    fn create(path: &Path, sample_rate: u32) -> Result<Self> {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        Ok(Self {
+            writer: hound::WavWriter::create(path, spec)?,
+        })
+    }
+}
+
+impl Encoder for WavEncoder {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for &sample in samples {
+            self.writer.write_sample(to_i16(sample))?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.writer.finalize()?;
+        Ok(())
+    }
+}
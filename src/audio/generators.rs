@@ -0,0 +1,242 @@
+use crate::static_data::{Generator, Sound, ToneParams, Waveform};
+use rodio::Source;
+use std::f64::consts::TAU;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SAMPLE_RATE: u32 = 48000;
+const CHANNELS: u16 = 2;
+// Rows summed by the Voss-McCartney pink noise generator
+const PINK_ROWS: usize = 16;
+
+/// Builds the endless source for a synthesized sound.
+pub fn source(generator: Generator, sound: &Sound) -> Box<dyn Source<Item = f32> + Send> {
+    let tone = sound.tone.unwrap_or_default();
+    match generator {
+        Generator::White => Box::new(NoiseSource::new(Noise::White)),
+        Generator::Pink => Box::new(NoiseSource::new(Noise::Pink)),
+        Generator::Brown => Box::new(NoiseSource::new(Noise::Brown)),
+        Generator::Blue => Box::new(NoiseSource::new(Noise::Blue)),
+        Generator::Violet => Box::new(NoiseSource::new(Noise::Violet)),
+        Generator::Binaural => Box::new(ToneSource::new(tone, false)),
+        Generator::Isochronic => Box::new(ToneSource::new(tone, true)),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Noise {
+    White,
+    Pink,
+    Brown,
+    Blue,
+    Violet,
+}
+
+// xorshift32, plenty for noise and avoids pulling in a rand crate
+pub(super) struct Rng(u32);
+
+impl Rng {
+    pub(super) fn seeded() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        Self(nanos | 1)
+    }
+
+    pub(super) fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// Uniform sample in -1.0..1.0.
+    pub(super) fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+    }
+}
+
+// Per-channel filter state, so left and right are uncorrelated
+#[derive(Default)]
+struct ChannelState {
+    pink_rows: [f32; PINK_ROWS],
+    pink_sum: f32,
+    counter: u32,
+    brown: f32,
+    previous: f32,
+}
+
+impl ChannelState {
+    fn pink(&mut self, rng: &mut Rng) -> f32 {
+        // Each step refreshes the row picked by the counter's trailing zeros,
+        // so row n changes every 2^n samples
+        self.counter = self.counter.wrapping_add(1);
+        let row = (self.counter.trailing_zeros() as usize).min(PINK_ROWS - 1);
+        let value = rng.next_f32();
+        self.pink_sum += value - self.pink_rows[row];
+        self.pink_rows[row] = value;
+        (self.pink_sum + rng.next_f32()) / (PINK_ROWS + 1) as f32
+    }
+
+    fn next(&mut self, noise: Noise, rng: &mut Rng) -> f32 {
+        match noise {
+            Noise::White => rng.next_f32() * 0.5,
+            Noise::Pink => self.pink(rng) * 2.5,
+            Noise::Brown => {
+                // Leaky integrator, the leak keeps it from drifting off
+                self.brown = (self.brown + 0.02 * rng.next_f32()) / 1.02;
+                self.brown * 3.5
+            }
+            // Differentiating pink adds 6 dB/octave, giving +3 dB/octave
+            Noise::Blue => {
+                let pink = self.pink(rng);
+                let blue = pink - self.previous;
+                self.previous = pink;
+                blue * 2.5
+            }
+            Noise::Violet => {
+                let white = rng.next_f32();
+                let violet = white - self.previous;
+                self.previous = white;
+                violet * 0.25
+            }
+        }
+    }
+}
+
+struct NoiseSource {
+    noise: Noise,
+    channels: [ChannelState; CHANNELS as usize],
+    channel: usize,
+    rng: Rng,
+}
+
+impl NoiseSource {
+    fn new(noise: Noise) -> Self {
+        Self {
+            noise,
+            channels: Default::default(),
+            channel: 0,
+            rng: Rng::seeded(),
+        }
+    }
+}
+
+impl Iterator for NoiseSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.channels[self.channel].next(self.noise, &mut self.rng);
+        self.channel = (self.channel + 1) % CHANNELS as usize;
+        Some(sample.clamp(-1.0, 1.0))
+    }
+}
+
+impl Source for NoiseSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// One cycle of the waveform at `phase` (0.0..1.0)
+fn wave(waveform: Waveform, phase: f64) -> f64 {
+    match waveform {
+        Waveform::Sine => (phase * TAU).sin(),
+        Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        Waveform::Square if phase < 0.5 => 1.0,
+        Waveform::Square => -1.0,
+    }
+}
+
+/// Binaural beats play the carrier slightly detuned in each ear, isochronic
+/// tones pulse the same carrier in both ears at the beat rate.
+struct ToneSource {
+    params: ToneParams,
+    isochronic: bool,
+    left_phase: f64,
+    right_phase: f64,
+    gate_phase: f64,
+    // The right sample of the current frame, waiting to be returned
+    pending_right: Option<f32>,
+}
+
+impl ToneSource {
+    fn new(params: ToneParams, isochronic: bool) -> Self {
+        Self {
+            params,
+            isochronic,
+            left_phase: 0.0,
+            right_phase: 0.0,
+            gate_phase: 0.0,
+            pending_right: None,
+        }
+    }
+
+    fn advance(phase: &mut f64, frequency: f64) {
+        *phase = (*phase + frequency / SAMPLE_RATE as f64).rem_euclid(1.0);
+    }
+}
+
+impl Iterator for ToneSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.pending_right.take() {
+            return Some(right);
+        }
+
+        let carrier = self.params.carrier as f64;
+        let beat = self.params.beat as f64;
+        let waveform = self.params.waveform;
+
+        let (left, right) = if self.isochronic {
+            // A squashed sine makes a pulse with soft edges, a hard gate clicks
+            let gate = (((self.gate_phase * TAU).sin() * 3.0).clamp(-1.0, 1.0) + 1.0) / 2.0;
+            Self::advance(&mut self.gate_phase, beat);
+            let tone = wave(waveform, self.left_phase) * gate;
+            Self::advance(&mut self.left_phase, carrier);
+            (tone, tone)
+        } else {
+            let left = wave(waveform, self.left_phase);
+            let right = wave(waveform, self.right_phase);
+            Self::advance(&mut self.left_phase, carrier - beat / 2.0);
+            Self::advance(&mut self.right_phase, carrier + beat / 2.0);
+            (left, right)
+        };
+
+        self.pending_right = Some((right * 0.3) as f32);
+        Some((left * 0.3) as f32)
+    }
+}
+
+impl Source for ToneSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
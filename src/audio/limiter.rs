@@ -0,0 +1,114 @@
+use rodio::Source;
+use std::time::Duration;
+
+// Level the limiter holds peaks to, a little under full scale
+const THRESHOLD: f32 = 0.9;
+const ATTACK_SECONDS: f32 = 0.002;
+const RELEASE_SECONDS: f32 = 0.25;
+
+/// Sits on the master bus after every sound is mixed. Loud peaks pull the
+/// gain down quickly and it recovers slowly, and whatever slips past the
+/// attack is rounded off by a soft clipper instead of clipping hard.
+///
+/// The mixer ends whenever nothing is playing, so silence is produced then
+/// to keep the bus alive for the next sound.
+pub struct Limiter<S> {
+    input: S,
+    enabled: bool,
+    channels: usize,
+    sample_rate: u32,
+    gain: f32,
+    attack: f32,
+    release: f32,
+    frame: Vec<f32>,
+    position: usize,
+}
+
+impl<S: Source<Item = f32>> Limiter<S> {
+    pub fn new(input: S, enabled: bool) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let sample_rate = input.sample_rate();
+        // One-pole coefficients per frame
+        let coefficient = |seconds: f32| 1.0 - (-1.0 / (seconds * sample_rate as f32)).exp();
+        Self {
+            input,
+            enabled,
+            channels,
+            sample_rate,
+            gain: 1.0,
+            attack: coefficient(ATTACK_SECONDS),
+            release: coefficient(RELEASE_SECONDS),
+            frame: vec![0.0; channels],
+            position: channels,
+        }
+    }
+
+    fn next_frame(&mut self) {
+        for sample in &mut self.frame {
+            *sample = self.input.next().unwrap_or(0.0);
+        }
+        if !self.enabled {
+            return;
+        }
+
+        let peak = self.frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let target = if peak > THRESHOLD {
+            THRESHOLD / peak
+        } else {
+            1.0
+        };
+        let speed = if target < self.gain {
+            self.attack
+        } else {
+            self.release
+        };
+        self.gain += (target - self.gain) * speed;
+
+        for sample in &mut self.frame {
+            *sample = soft_clip(*sample * self.gain);
+        }
+    }
+}
+
+// Linear up to the threshold, then bends smoothly towards full scale
+fn soft_clip(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= THRESHOLD {
+        return sample;
+    }
+    let headroom = 1.0 - THRESHOLD;
+    let level = THRESHOLD + headroom * ((level - THRESHOLD) / headroom).tanh();
+    level.copysign(sample)
+}
+
+impl<S: Source<Item = f32>> Iterator for Limiter<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.channels {
+            self.next_frame();
+            self.position = 0;
+        }
+        let sample = self.frame[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Limiter<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
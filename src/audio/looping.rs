@@ -0,0 +1,294 @@
+use super::cache::SampleCache;
+use crate::static_data::LoopPoints;
+use anyhow::Result;
+use rodio::Source;
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_PI_2;
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::time::Duration;
+
+type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+/// Loops a file endlessly, trimmed to the loop markers, overlapping the tail
+/// of each pass with the head of the next.
+///
+/// The file is reopened for every pass instead of being held in memory, so
+/// only the crossfade itself is buffered. A helper thread keeps the next pass
+/// opened and wound to the loop start, so the audio thread never touches the
+/// disk. Output lags the decoder by the crossfade length, which is how the
+/// tail is known before the file ends.
+pub struct LoopSource {
+    path: String,
+    current: BoxedSource,
+    next_pass: Receiver<BoxedSource>,
+    // Taken from `next_pass` ahead of time by `wait_for_next_pass`
+    #[cfg(test)]
+    queued: Option<BoxedSource>,
+    channels: u16,
+    sample_rate: u32,
+    // Loop markers in samples (all channels), aligned to whole frames
+    start: u64,
+    end: Option<u64>,
+    crossfade: usize,
+    // Samples read from the current pass, counted from the loop start
+    position: u64,
+    // Decoded samples not yet played
+    ahead: VecDeque<f32>,
+    // The end of the previous pass while it fades out
+    tail: VecDeque<f32>,
+    tail_len: usize,
+    // Samples of silence left to finish a frame while the next pass opens
+    silence: usize,
+    finished: bool,
+}
+
+impl LoopSource {
+    pub fn open(path: &str, points: LoopPoints, cache: SampleCache) -> Result<Self> {
+        let mut current = cache.open(path)?;
+        let channels = current.channels().max(1);
+        let sample_rate = current.sample_rate();
+
+        let frame = channels as u64;
+        let samples_at = |seconds: f32| {
+            let frames = (seconds.max(0.0) as f64 * sample_rate as f64) as u64;
+            frames * frame
+        };
+        let start = points.start.map(samples_at).unwrap_or(0);
+        let end = points.end.map(samples_at).filter(|&end| end > start);
+        let crossfade = samples_at(points.crossfade_ms as f32 / 1000.0) as usize;
+
+        skip_intro(&mut current, start, channels, sample_rate);
+        // One pass waits in the channel, the helper blocks holding the next
+        let (tx, next_pass) = mpsc::sync_channel(1);
+        let pass = Pass {
+            path: path.to_string(),
+            cache,
+            start,
+            channels,
+            sample_rate,
+        };
+        std::thread::spawn(move || pass.prepare(tx));
+
+        Ok(Self {
+            path: path.to_string(),
+            current,
+            next_pass,
+            #[cfg(test)]
+            queued: None,
+            channels,
+            sample_rate,
+            start,
+            end,
+            crossfade,
+            position: 0,
+            ahead: VecDeque::with_capacity(crossfade),
+            tail: VecDeque::new(),
+            tail_len: 0,
+            silence: 0,
+            finished: false,
+        })
+    }
+
+    /// Blocks until the pass after the current one is open, so tests don't
+    /// race the helper.
+    #[cfg(test)]
+    fn wait_for_next_pass(&mut self) {
+        if self.queued.is_none() {
+            self.queued = self.next_pass.recv().ok();
+        }
+    }
+
+    fn try_next_pass(&mut self) -> Result<BoxedSource, TryRecvError> {
+        #[cfg(test)]
+        if let Some(pass) = self.queued.take() {
+            return Ok(pass);
+        }
+        self.next_pass.try_recv()
+    }
+
+    // Next sample of the current pass, stopping at the loop end
+    fn pull(&mut self) -> Option<f32> {
+        if self
+            .end
+            .is_some_and(|end| self.start + self.position >= end)
+        {
+            return None;
+        }
+        let sample = self.current.next()?;
+        self.position += 1;
+        Some(sample)
+    }
+
+    // Starts the next pass, keeping what was left of this one as the tail
+    fn restart(&mut self, pass: BoxedSource) {
+        self.current = pass;
+        self.position = 0;
+        self.tail = std::mem::take(&mut self.ahead);
+        self.tail_len = self.tail.len();
+    }
+
+    fn crossfade_step(&mut self) -> f32 {
+        let channels = self.channels as usize;
+        let frame = (self.tail_len - self.tail.len()) / channels;
+        let frames = (self.tail_len / channels).max(1);
+        let t = frame as f32 / frames as f32;
+
+        // Reading two samples per one played refills the lookahead by the
+        // time the tail runs out
+        for _ in 0..2 {
+            if let Some(sample) = self.pull() {
+                self.ahead.push_back(sample);
+            }
+        }
+        let head = self.ahead.pop_front().unwrap_or(0.0);
+        let tail = self.tail.pop_front().unwrap_or(0.0);
+
+        // Equal power, so uncorrelated material doesn't dip in the middle
+        tail * (t * FRAC_PI_2).cos() + head * (t * FRAC_PI_2).sin()
+    }
+}
+
+impl Iterator for LoopSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.finished {
+            return None;
+        }
+        if self.silence > 0 {
+            self.silence -= 1;
+            return Some(0.0);
+        }
+        if !self.tail.is_empty() {
+            return Some(self.crossfade_step());
+        }
+
+        while self.ahead.len() < self.crossfade {
+            match self.pull() {
+                Some(sample) => self.ahead.push_back(sample),
+                None => break,
+            }
+        }
+        if let Some(sample) = self.pull() {
+            self.ahead.push_back(sample);
+            return self.ahead.pop_front();
+        }
+
+        // The pass is over and `ahead` holds its tail
+        if self.position == 0 {
+            // Nothing was read from the last pass, looping would spin forever
+            log::warn!("Nothing to loop in '{}'", self.path);
+            self.finished = true;
+            return None;
+        }
+        match self.try_next_pass() {
+            Ok(pass) => self.restart(pass),
+            // Only when opening takes longer than a whole pass plays
+            Err(TryRecvError::Empty) => {
+                self.silence = self.channels as usize - 1;
+                return Some(0.0);
+            }
+            // The helper has already logged why
+            Err(TryRecvError::Disconnected) => {
+                self.finished = true;
+                return None;
+            }
+        }
+        self.next()
+    }
+}
+
+impl Source for LoopSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// What the helper thread needs to open passes
+struct Pass {
+    path: String,
+    cache: SampleCache,
+    start: u64,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Pass {
+    // Opens pass after pass until the loop is dropped
+    fn prepare(self, tx: SyncSender<BoxedSource>) {
+        loop {
+            let mut source = match self.cache.open(&self.path) {
+                Ok(source) => source,
+                Err(e) => {
+                    log::error!("Failed to reopen '{}' for looping: {}", self.path, e);
+                    return;
+                }
+            };
+            skip_intro(&mut source, self.start, self.channels, self.sample_rate);
+            if tx.send(source).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// Winds a fresh pass forward to the loop start, given in samples
+fn skip_intro(source: &mut BoxedSource, start: u64, channels: u16, sample_rate: u32) {
+    if start == 0 {
+        return;
+    }
+    let seconds = (start / channels as u64) as f64 / sample_rate as f64;
+    // Decoders that can't seek are read through instead
+    if source.try_seek(Duration::from_secs_f64(seconds)).is_err() {
+        for _ in 0..start {
+            if source.next().is_none() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::decoder;
+    use crate::audio::testing::write_wav;
+
+    const RATE: u32 = 8000;
+
+    #[test]
+    fn loops_the_marked_region_without_gaps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ramp.wav");
+        write_wav(&path, 2, RATE, (0..2000).flat_map(|i| [i, -i]));
+        let path = path.to_str().unwrap();
+        let file: Vec<f32> = decoder::open(path).unwrap().collect();
+
+        let points = LoopPoints {
+            crossfade_ms: 0,
+            start: Some(0.0625),
+            end: Some(0.1875),
+        };
+        let mut source = LoopSource::open(path, points, SampleCache::default()).unwrap();
+
+        // Frames 500 to 1500, in interleaved stereo
+        let region = &file[1000..3000];
+        for _ in 0..3 {
+            source.wait_for_next_pass();
+            let pass: Vec<f32> = source.by_ref().take(region.len()).collect();
+            assert_eq!(pass, region);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use super::decoder;
+use super::effects::{Biquad, Coefficients};
+use anyhow::Result;
+use rodio::source::UniformSourceIterator;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::UNIX_EPOCH;
+
+// Long recordings are judged by their start, decoding hours of audio to
+// measure it would keep a core busy for minutes
+const MEASURE_SECONDS: u32 = 600;
+// Bounds on the correction, so near-silent files aren't blown up
+const MAX_BOOST_DB: f32 = 12.0;
+const MAX_CUT_DB: f32 = 24.0;
+
+/// Matches the perceived loudness of files by measuring each one once, in
+/// the background, and scaling it towards a target.
+pub struct Normalizer {
+    target: f32,
+    cache: LoudnessCache,
+    cache_path: PathBuf,
+    // Sound id -> file it plays
+    sounds: HashMap<String, String>,
+    pending: HashSet<String>,
+    // Files that couldn't be measured, not retried until restart
+    failed: HashSet<String>,
+    tx: Sender<(String, Result<f32>)>,
+    rx: Receiver<(String, Result<f32>)>,
+}
+
+impl Normalizer {
+    /// Measurements are kept between runs in the file at `cache_path`.
+    pub fn new(target: f32, cache_path: &Path) -> Self {
+        let (tx, rx) = channel();
+        Self {
+            target,
+            cache: LoudnessCache::load(cache_path),
+            cache_path: cache_path.to_path_buf(),
+            sounds: HashMap::new(),
+            pending: HashSet::new(),
+            failed: HashSet::new(),
+            tx,
+            rx,
+        }
+    }
+
+    /// Starts measuring a sound's file unless the cache already knows it.
+    pub fn track(&mut self, id: &str, path: &str) {
+        self.sounds.insert(id.to_string(), path.to_string());
+        if self.pending.contains(path)
+            || self.failed.contains(path)
+            || self.cache.get(path).is_some()
+        {
+            return;
+        }
+
+        log::info!("Measuring loudness of '{}'", path);
+        self.pending.insert(path.to_string());
+        let tx = self.tx.clone();
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            let result = measure(&path);
+            let _ = tx.send((path, result));
+        });
+    }
+
+    /// Whether a file's loudness is already known, so tracking it is instant.
+    pub fn is_measured(&self, path: &str) -> bool {
+        self.cache.get(path).is_some()
+    }
+
+    /// Measures a sound's file right away unless the cache knows it, for
+    /// rendering where nothing plays while waiting.
+    pub fn track_now(&mut self, id: &str, path: &str) {
+        self.sounds.insert(id.to_string(), path.to_string());
+        if self.cache.get(path).is_some() {
+            return;
+        }
+        log::info!("Measuring loudness of '{}'", path);
+        match measure(path) {
+            Ok(lufs) => {
+                self.cache.insert(path, lufs);
+                if let Err(e) = self.cache.save(&self.cache_path) {
+                    log::warn!("Failed to save loudness cache: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Could not measure loudness of '{}': {}", path, e),
+        }
+    }
+
+    /// The gain that brings a sound to the target, 1.0 until it is measured.
+    pub fn gain(&self, id: &str) -> f32 {
+        // The file was checked against the cache in `track`
+        let Some(lufs) = self
+            .sounds
+            .get(id)
+            .and_then(|path| self.cache.files.get(path))
+            .map(|entry| entry.lufs)
+        else {
+            return 1.0;
+        };
+        let db = (self.target - lufs).clamp(-MAX_CUT_DB, MAX_BOOST_DB);
+        10f32.powf(db / 20.0)
+    }
+
+    /// Collects finished measurements, returns whether any gain changed.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((path, result)) = self.rx.try_recv() {
+            self.pending.remove(&path);
+            match result {
+                Ok(lufs) => {
+                    log::info!("'{}' measures {:.1} LUFS", path, lufs);
+                    self.cache.insert(&path, lufs);
+                    changed = true;
+                }
+                Err(e) => {
+                    log::warn!("Could not measure loudness of '{}': {}", path, e);
+                    self.failed.insert(path);
+                }
+            }
+        }
+        if changed {
+            if let Err(e) = self.cache.save(&self.cache_path) {
+                log::warn!("Failed to save loudness cache: {}", e);
+            }
+        }
+        changed
+    }
+}
+
+/// Integrated loudness in LUFS, following ITU-R BS.1770 / EBU R128: K-weighted
+/// 400 ms blocks overlapping by 75%, with the absolute and relative gates.
+fn measure(path: &str) -> Result<f32> {
+    let source = decoder::open(path)?;
+    let rate = source.sample_rate();
+    let stereo: UniformSourceIterator<_, f32> = UniformSourceIterator::new(source, 2, rate);
+
+    let mut shelf = Biquad::new(Coefficients::shelf(1681.97, 4.0, rate, true));
+    let mut high_pass = Biquad::new(Coefficients::high_pass(38.13, 0.5, rate));
+
+    // Mean square of every 100 ms step, summed over both channels
+    let step = (rate / 10).max(1) as usize * 2;
+    let mut steps = Vec::new();
+    let mut sum = 0.0f64;
+    let mut count = 0;
+    for (i, sample) in stereo
+        .take(MEASURE_SECONDS as usize * rate as usize * 2)
+        .enumerate()
+    {
+        let channel = i % 2;
+        let weighted = high_pass.process(channel, shelf.process(channel, sample));
+        sum += (weighted as f64).powi(2);
+        count += 1;
+        if count == step {
+            steps.push(sum / (step / 2) as f64);
+            sum = 0.0;
+            count = 0;
+        }
+    }
+
+    let blocks: Vec<f64> = steps
+        .windows(4)
+        .map(|w| w.iter().sum::<f64>() / 4.0)
+        .collect();
+    let loudness = |power: f64| -0.691 + 10.0 * power.log10();
+    let gated_mean = |threshold: f64| {
+        let gated: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&p| loudness(p) > threshold)
+            .collect();
+        (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+    };
+
+    let Some(absolute) = gated_mean(-70.0) else {
+        anyhow::bail!("File is silent or shorter than 400 ms");
+    };
+    let power = gated_mean(loudness(absolute) - 10.0).unwrap_or(absolute);
+    Ok(loudness(power) as f32)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified: u64,
+    lufs: f32,
+}
+
+// Measurements keyed by path, dropped when the file's size or mtime changes
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LoudnessCache {
+    #[serde(default)]
+    files: HashMap<String, CacheEntry>,
+}
+
+impl LoudnessCache {
+    fn load(path: &Path) -> Self {
+        // Like the session, a broken cache only costs a re-measure
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Only trusts entries whose file hasn't changed since it was measured
+    fn get(&self, path: &str) -> Option<f32> {
+        let entry = self.files.get(path)?;
+        (file_stamp(path)? == (entry.size, entry.modified)).then_some(entry.lufs)
+    }
+
+    fn insert(&mut self, path: &str, lufs: f32) {
+        if let Some((size, modified)) = file_stamp(path) {
+            self.files.insert(
+                path.to_string(),
+                CacheEntry {
+                    size,
+                    modified,
+                    lufs,
+                },
+            );
+        }
+    }
+}
+
+fn file_stamp(path: &str) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((metadata.len(), modified))
+}
@@ -0,0 +1,727 @@
+use super::encoder::Encoder;
+use anyhow::{Context, Result};
+use audiopus::coder::Encoder as OpusCoder;
+use audiopus::{ffi, Application, Bitrate, Channels};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use ogg::PacketReader;
+use rodio::Source;
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::Path;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+// Opus is always decoded at 48 kHz, whatever the input rate in the header
+// says, and granule positions, pre-skip and padding count frames at it
+const OPUS_RATE: u32 = 48000;
+// The longest packet Opus allows is 120 ms
+const MAX_PACKET_FRAMES: usize = 5760;
+// 20 ms at 48 kHz, the frame size Opus is usually run with
+const OPUS_FRAME: usize = 960;
+const OPUS_BITRATE: i32 = 128_000;
+const OPUS_MAX_PACKET: usize = 4000;
+
+/// What the OpusHead packet says about a stream (RFC 7845, section 5.1).
+struct Header {
+    channels: usize,
+    pre_skip: u64,
+    // Q7.8 dB
+    output_gain: i16,
+    family: u8,
+    streams: u8,
+    coupled: u8,
+    mapping: Vec<u8>,
+}
+
+impl Header {
+    fn parse(packet: &[u8]) -> Result<Self> {
+        anyhow::ensure!(
+            packet.len() >= 19 && packet.starts_with(b"OpusHead"),
+            "Not an Ogg Opus stream"
+        );
+        anyhow::ensure!(
+            packet[8] >> 4 == 0,
+            "Unsupported Opus version {}",
+            packet[8]
+        );
+        let channels = packet[9] as usize;
+        anyhow::ensure!(channels > 0, "Opus stream has no channels");
+        let family = packet[18];
+
+        let (streams, coupled, mapping) = if family == 0 {
+            anyhow::ensure!(
+                channels <= 2,
+                "{} channels need a channel mapping",
+                channels
+            );
+            (1, channels as u8 - 1, (0..channels as u8).collect())
+        } else {
+            let table = packet
+                .get(19..21 + channels)
+                .context("Opus channel mapping table is cut short")?;
+            (table[0], table[1], table[2..].to_vec())
+        };
+
+        Ok(Self {
+            channels,
+            pre_skip: u16::from_le_bytes([packet[10], packet[11]]) as u64,
+            output_gain: i16::from_le_bytes([packet[16], packet[17]]),
+            family,
+            streams,
+            coupled,
+            mapping,
+        })
+    }
+}
+
+/// One Opus packet as a container hands it over.
+pub struct Packet {
+    pub data: Vec<u8>,
+    /// Padding at the end of the packet to throw away, in 48 kHz frames.
+    pub discard: u64,
+}
+
+/// Where an [`OpusSource`] gets its packets from, so Ogg and WebM files
+/// share the decoder.
+pub trait Packets: Send {
+    /// The next audio packet, or `None` at the end of the stream.
+    fn next_packet(&mut self) -> Result<Option<Packet>>;
+}
+
+/// Decodes an Opus stream into 48 kHz stereo.
+///
+/// Mono is spread to both sides and surround layouts are folded down, so
+/// every file reaches the mixer in the layout it plays in. The encoder's
+/// start-up delay and the padding after the last sample are cut off, which
+/// keeps looped files seamless.
+pub struct OpusSource {
+    packets: Box<dyn Packets>,
+    decoder: MultistreamDecoder,
+    channels: usize,
+    downmix: Vec<[f32; 2]>,
+    // Frames still to drop from the start
+    pre_skip: usize,
+    pcm: Vec<f32>,
+    // The current packet in stereo
+    output: Vec<f32>,
+    position: usize,
+    finished: bool,
+}
+
+impl OpusSource {
+    /// Decodes `packets` as described by the OpusHead packet `head`.
+    pub fn new(head: &[u8], packets: Box<dyn Packets>) -> Result<Self> {
+        let header = Header::parse(head)?;
+        let decoder = MultistreamDecoder::new(&header)?;
+        let gain = 10f32.powf(header.output_gain as f32 / 256.0 / 20.0);
+        let downmix = stereo_downmix(header.family, header.channels)
+            .into_iter()
+            .map(|[left, right]| [left * gain, right * gain])
+            .collect();
+
+        Ok(Self {
+            packets,
+            decoder,
+            channels: header.channels,
+            downmix,
+            pre_skip: header.pre_skip as usize,
+            pcm: vec![0.0; MAX_PACKET_FRAMES * header.channels],
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+        })
+    }
+
+    /// Reads an Ogg Opus file.
+    pub fn ogg<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self> {
+        let mut packets = PacketReader::new(reader);
+        let head = packets
+            .read_packet_expected()
+            .context("Failed to read the Opus header")?;
+        // The comment header only holds tags
+        packets
+            .read_packet_expected()
+            .context("Failed to read the Opus tags")?;
+        let packets = OggPackets {
+            packets,
+            page: VecDeque::new(),
+            granule: 0,
+            finished: false,
+        };
+        Self::new(&head.data, Box::new(packets))
+    }
+
+    fn decode_packet(&mut self, packet: Packet) -> Result<()> {
+        let frames = self.decoder.decode(&packet.data, &mut self.pcm)?;
+        let end = frames.saturating_sub(packet.discard as usize);
+        let start = self.pre_skip.min(end);
+        self.pre_skip -= self.pre_skip.min(frames);
+
+        self.output.clear();
+        self.position = 0;
+        let pcm = &self.pcm[start * self.channels..end * self.channels];
+        for frame in pcm.chunks_exact(self.channels) {
+            let (mut left, mut right) = (0.0, 0.0);
+            for (sample, [to_left, to_right]) in frame.iter().zip(&self.downmix) {
+                left += sample * to_left;
+                right += sample * to_right;
+            }
+            self.output.extend([left, right]);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for OpusSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while self.position == self.output.len() {
+            if self.finished {
+                return None;
+            }
+            let decoded = match self.packets.next_packet() {
+                Ok(Some(packet)) => self.decode_packet(packet),
+                Ok(None) => {
+                    self.finished = true;
+                    return None;
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = decoded {
+                log::error!("Opus decoding stopped: {:#}", e);
+                self.finished = true;
+                return None;
+            }
+        }
+        self.position += 1;
+        Some(self.output[self.position - 1])
+    }
+}
+
+impl Source for OpusSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        OPUS_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// Ogg only tells where the audio ends through the granule position of the
+// last page, so packets are handed out a page at a time to know which of
+// them the padding falls in.
+struct OggPackets<R: Read + Seek> {
+    packets: PacketReader<R>,
+    page: VecDeque<Packet>,
+    // Where the previous page ended
+    granule: u64,
+    finished: bool,
+}
+
+impl<R: Read + Seek> OggPackets<R> {
+    fn read_page(&mut self) -> Result<()> {
+        loop {
+            let Some(packet) = self.packets.read_packet()? else {
+                self.finished = true;
+                return Ok(());
+            };
+            let (last_in_stream, last_in_page) = (packet.last_in_stream(), packet.last_in_page());
+            let granule = packet.absgp_page();
+            if !packet.data.is_empty() {
+                self.page.push_back(Packet {
+                    data: packet.data,
+                    discard: 0,
+                });
+            }
+
+            if last_in_stream {
+                self.finished = true;
+                let frames: u64 = self.page.iter().map(|p| packet_frames(&p.data)).sum();
+                let mut padding = (self.granule + frames).saturating_sub(granule);
+                for packet in self.page.iter_mut().rev() {
+                    packet.discard = padding.min(packet_frames(&packet.data));
+                    padding -= packet.discard;
+                }
+                return Ok(());
+            }
+            if last_in_page {
+                self.granule = granule;
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek + Send> Packets for OggPackets<R> {
+    fn next_packet(&mut self) -> Result<Option<Packet>> {
+        while self.page.is_empty() && !self.finished {
+            self.read_page()?;
+        }
+        Ok(self.page.pop_front())
+    }
+}
+
+// The length of a packet in 48 kHz frames, read from its table of contents
+fn packet_frames(packet: &[u8]) -> u64 {
+    let frames = unsafe {
+        ffi::opus_packet_get_nb_samples(packet.as_ptr(), packet.len() as i32, OPUS_RATE as i32)
+    };
+    frames.max(0) as u64
+}
+
+// How much of each channel goes to the left and right speaker. Family 1
+// uses the Vorbis channel order; other families don't say where channels
+// go, so they are mixed evenly.
+fn stereo_downmix(family: u8, channels: usize) -> Vec<[f32; 2]> {
+    const L: [f32; 2] = [1.0, 0.0];
+    const R: [f32; 2] = [0.0, 1.0];
+    const C: [f32; 2] = [FRAC_1_SQRT_2, FRAC_1_SQRT_2];
+    const SL: [f32; 2] = [FRAC_1_SQRT_2, 0.0];
+    const SR: [f32; 2] = [0.0, FRAC_1_SQRT_2];
+    const RC: [f32; 2] = [0.5, 0.5];
+    // The LFE channel is left out, as in the usual Lo/Ro downmix
+    const LFE: [f32; 2] = [0.0, 0.0];
+
+    let layout: Vec<[f32; 2]> = match (family, channels) {
+        (_, 1) => return vec![[1.0, 1.0]],
+        (0 | 1, 2) => return vec![L, R],
+        (1, 3) => vec![L, C, R],
+        (1, 4) => vec![L, R, SL, SR],
+        (1, 5) => vec![L, C, R, SL, SR],
+        (1, 6) => vec![L, C, R, SL, SR, LFE],
+        (1, 7) => vec![L, C, R, SL, SR, RC, LFE],
+        (1, 8) => vec![L, C, R, SL, SR, SL, SR, LFE],
+        _ => vec![[1.0, 1.0]; channels],
+    };
+    // Scaled so a sound playing on every channel doesn't get any louder
+    let total: f32 = layout.iter().map(|[left, _]| left).sum();
+    layout
+        .into_iter()
+        .map(|[left, right]| [left / total, right / total])
+        .collect()
+}
+
+/// libopus's multistream decoder, which also covers plain mono and stereo
+/// streams as a single stream.
+struct MultistreamDecoder(NonNull<ffi::OpusMSDecoder>);
+
+// The decoder state is plain memory owned by this handle
+unsafe impl Send for MultistreamDecoder {}
+
+impl MultistreamDecoder {
+    fn new(header: &Header) -> Result<Self> {
+        anyhow::ensure!(
+            header.mapping.len() == header.channels
+                && header.streams > 0
+                && header.coupled <= header.streams,
+            "Invalid Opus channel mapping"
+        );
+        let mut error = 0;
+        let decoder = unsafe {
+            ffi::opus_multistream_decoder_create(
+                OPUS_RATE as i32,
+                header.channels as i32,
+                header.streams as i32,
+                header.coupled as i32,
+                header.mapping.as_ptr(),
+                &mut error,
+            )
+        };
+        check(error).context("Failed to create the Opus decoder")?;
+        NonNull::new(decoder)
+            .map(Self)
+            .context("Failed to create the Opus decoder")
+    }
+
+    /// Decodes one packet into `pcm`, returning the number of frames.
+    fn decode(&mut self, packet: &[u8], pcm: &mut [f32]) -> Result<usize> {
+        let frames = unsafe {
+            ffi::opus_multistream_decode_float(
+                self.0.as_ptr(),
+                packet.as_ptr(),
+                packet.len() as i32,
+                pcm.as_mut_ptr(),
+                MAX_PACKET_FRAMES as i32,
+                0,
+            )
+        };
+        check(frames).context("Corrupt Opus packet")?;
+        Ok(frames as usize)
+    }
+}
+
+impl Drop for MultistreamDecoder {
+    fn drop(&mut self) {
+        unsafe { ffi::opus_multistream_decoder_destroy(self.0.as_ptr()) }
+    }
+}
+
+fn check(code: i32) -> Result<()> {
+    if code >= 0 {
+        return Ok(());
+    }
+    let message = unsafe { CStr::from_ptr(ffi::opus_strerror(code)) };
+    anyhow::bail!("{}", message.to_string_lossy())
+}
+
+/// Writes interleaved stereo to an Ogg Opus file as laid out in RFC 7845.
+pub struct OpusEncoder {
+    coder: OpusCoder,
+    writer: PacketWriter<BufWriter<File>>,
+    pending: Vec<f32>,
+    pre_skip: u64,
+    // Frames handed to the encoder so far, padding not included
+    frames: u64,
+    packets: u64,
+}
+
+impl OpusEncoder {
+    // Any serial number works with a single stream in the file
+    const SERIAL: u32 = 0x7461_6e69;
+
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self> {
+        anyhow::ensure!(sample_rate == OPUS_RATE, "Opus files are always 48 kHz");
+        let mut coder = OpusCoder::new(
+            audiopus::SampleRate::Hz48000,
+            Channels::Stereo,
+            Application::Audio,
+        )
+        .context("Failed to create the Opus encoder")?;
+        coder.set_bitrate(Bitrate::BitsPerSecond(OPUS_BITRATE))?;
+        let pre_skip = coder.lookahead()? as u64;
+
+        let mut head = b"OpusHead".to_vec();
+        head.push(1); // version
+        head.push(2); // channels
+        head.extend((pre_skip as u16).to_le_bytes());
+        head.extend(sample_rate.to_le_bytes());
+        head.extend(0i16.to_le_bytes()); // output gain
+        head.push(0); // mono or stereo mapping
+
+        let vendor = format!("tanin {}", env!("CARGO_PKG_VERSION"));
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend((vendor.len() as u32).to_le_bytes());
+        tags.extend(vendor.as_bytes());
+        tags.extend(0u32.to_le_bytes()); // no comments
+
+        // Both headers sit on pages of their own
+        let mut writer = PacketWriter::new(BufWriter::new(File::create(path)?));
+        writer.write_packet(head.into(), Self::SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+        writer.write_packet(tags.into(), Self::SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+        Ok(Self {
+            coder,
+            writer,
+            pending: Vec::with_capacity(OPUS_FRAME * 2),
+            pre_skip,
+            frames: 0,
+            packets: 0,
+        })
+    }
+
+    fn write_packet(&mut self, end: PacketWriteEndInfo) -> Result<()> {
+        let mut packet = vec![0; OPUS_MAX_PACKET];
+        let len = self.coder.encode_float(&self.pending, &mut packet)?;
+        packet.truncate(len);
+        self.packets += 1;
+        // The granule position counts decoded frames, and on the last page
+        // stops short of the padding so players drop it again
+        let granule = (self.packets * OPUS_FRAME as u64).min(self.pre_skip + self.frames);
+        self.writer
+            .write_packet(packet.into(), Self::SERIAL, end, granule)?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl Encoder for OpusEncoder {
+    fn write(&mut self, samples: &[f32]) -> Result<()> {
+        for frame in samples.chunks_exact(2) {
+            self.pending.extend_from_slice(frame);
+            if self.pending.len() == OPUS_FRAME * 2 {
+                self.frames += OPUS_FRAME as u64;
+                self.write_packet(PacketWriteEndInfo::NormalPacket)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.frames += (self.pending.len() / 2) as u64;
+        // The encoder lags `pre_skip` frames behind, silence pushes those out
+        let needed = (self.pre_skip + self.frames).div_ceil(OPUS_FRAME as u64);
+        while self.packets < needed {
+            self.pending.resize(OPUS_FRAME * 2, 0.0);
+            let end = if self.packets + 1 == needed {
+                PacketWriteEndInfo::EndStream
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            self.write_packet(end)?;
+        }
+        self.writer.inner_mut().flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+    use std::io::BufReader;
+
+    fn sine(frames: usize, channels: usize, hz: f32) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let sample = 0.5 * (TAU * hz * i as f32 / 48000.0).sin();
+                std::iter::repeat_n(sample, channels)
+            })
+            .collect()
+    }
+
+    // Not a whole number of Opus frames
+    const FRAMES: usize = 24_123;
+
+    // `FRAMES` interleaved frames with one tone per channel, `None` for silence
+    fn channels(tones: &[Option<(f32, f32)>]) -> Vec<f32> {
+        (0..FRAMES)
+            .flat_map(|i| {
+                tones.iter().map(move |tone| match tone {
+                    Some((amplitude, hz)) => amplitude * (TAU * hz * i as f32 / 48000.0).sin(),
+                    None => 0.0,
+                })
+            })
+            .collect()
+    }
+
+    /// Writes an Ogg Opus file by hand, one 20 ms packet at a time, so the
+    /// header can say anything. `encode` turns one packet of interleaved
+    /// samples into Opus data.
+    fn write_ogg(
+        path: &Path,
+        head: &Header,
+        input_rate: u32,
+        samples: &[f32],
+        mut encode: impl FnMut(&[f32], &mut [u8]) -> usize,
+    ) {
+        let mut head_packet = b"OpusHead".to_vec();
+        head_packet.push(1);
+        head_packet.push(head.channels as u8);
+        head_packet.extend((head.pre_skip as u16).to_le_bytes());
+        head_packet.extend(input_rate.to_le_bytes());
+        head_packet.extend(0i16.to_le_bytes());
+        head_packet.push(head.family);
+        if head.family != 0 {
+            head_packet.push(head.streams);
+            head_packet.push(head.coupled);
+            head_packet.extend(&head.mapping);
+        }
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(0u32.to_le_bytes());
+        tags.extend(0u32.to_le_bytes());
+
+        let mut writer = PacketWriter::new(BufWriter::new(File::create(path).unwrap()));
+        writer
+            .write_packet(head_packet.into(), 1, PacketWriteEndInfo::EndPage, 0)
+            .unwrap();
+        writer
+            .write_packet(tags.into(), 1, PacketWriteEndInfo::EndPage, 0)
+            .unwrap();
+
+        let total = head.pre_skip + (samples.len() / head.channels) as u64;
+        let packets = total.div_ceil(OPUS_FRAME as u64) as usize;
+        let mut padded = samples.to_vec();
+        padded.resize(packets * OPUS_FRAME * head.channels, 0.0);
+        for (i, frame) in padded.chunks(OPUS_FRAME * head.channels).enumerate() {
+            let mut packet = vec![0; OPUS_MAX_PACKET];
+            let len = encode(frame, &mut packet);
+            packet.truncate(len);
+            let end = if i + 1 == packets {
+                PacketWriteEndInfo::EndStream
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            let granule = ((i + 1) as u64 * OPUS_FRAME as u64).min(total);
+            writer.write_packet(packet.into(), 1, end, granule).unwrap();
+        }
+        writer.inner_mut().flush().unwrap();
+    }
+
+    fn write_with_coder(path: &Path, channels: Channels, input_rate: u32, samples: &[f32]) {
+        let mut coder =
+            OpusCoder::new(audiopus::SampleRate::Hz48000, channels, Application::Audio).unwrap();
+        coder
+            .set_bitrate(Bitrate::BitsPerSecond(OPUS_BITRATE))
+            .unwrap();
+        let count = channels as usize;
+        let head = Header {
+            channels: count,
+            pre_skip: coder.lookahead().unwrap() as u64,
+            output_gain: 0,
+            family: 0,
+            streams: 1,
+            coupled: count as u8 - 1,
+            mapping: (0..count as u8).collect(),
+        };
+        write_ogg(path, &head, input_rate, samples, |frame, packet| {
+            coder.encode_float(frame, packet).unwrap()
+        });
+    }
+
+    /// Decodes the whole file, checking it comes out as 48 kHz stereo.
+    fn decode(path: &Path) -> Vec<f32> {
+        let source = OpusSource::ogg(BufReader::new(File::open(path).unwrap())).unwrap();
+        assert_eq!(source.channels(), 2);
+        assert_eq!(source.sample_rate(), 48000);
+        let samples: Vec<f32> = source.collect();
+        assert_eq!(samples.len(), FRAMES * 2);
+        samples
+    }
+
+    // RMS of one output channel, away from the encoder's fade in and out
+    fn rms(samples: &[f32], channel: usize) -> f32 {
+        let middle = &samples[4800 * 2..samples.len() - 4800 * 2];
+        let sum: f32 = middle.iter().skip(channel).step_by(2).map(|s| s * s).sum();
+        (sum / (middle.len() / 2) as f32).sqrt()
+    }
+
+    fn assert_level(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < expected * 0.1,
+            "level {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn mono_plays_on_both_sides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mono.opus");
+        // Still decoded at 48 kHz, whatever rate the file came from
+        write_with_coder(
+            &path,
+            Channels::Mono,
+            16000,
+            &channels(&[Some((0.5, 440.0))]),
+        );
+
+        let samples = decode(&path);
+        assert!(samples.chunks_exact(2).all(|frame| frame[0] == frame[1]));
+        assert_level(rms(&samples, 0), 0.5 * FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn stereo_keeps_its_sides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stereo.opus");
+        let tones = [Some((0.5, 440.0)), Some((0.25, 660.0))];
+        write_with_coder(&path, Channels::Stereo, 44100, &channels(&tones));
+
+        let samples = decode(&path);
+        assert_level(rms(&samples, 0), 0.5 * FRAC_1_SQRT_2);
+        assert_level(rms(&samples, 1), 0.25 * FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn surround_is_mixed_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("surround.opus");
+        // 5.1 in Vorbis order: L, C, R, SL, SR, LFE
+        let head = Header {
+            channels: 6,
+            pre_skip: 312,
+            output_gain: 0,
+            family: 1,
+            streams: 4,
+            coupled: 2,
+            mapping: vec![0, 4, 1, 2, 3, 5],
+        };
+        let mut error = 0;
+        let encoder = unsafe {
+            ffi::opus_multistream_encoder_create(
+                48000,
+                6,
+                head.streams as i32,
+                head.coupled as i32,
+                head.mapping.as_ptr(),
+                ffi::OPUS_APPLICATION_AUDIO,
+                &mut error,
+            )
+        };
+        check(error).unwrap();
+        let tones = [
+            Some((0.5, 440.0)),
+            Some((0.4, 660.0)),
+            None,
+            None,
+            None,
+            // The LFE channel is left out of the mix
+            Some((0.5, 60.0)),
+        ];
+        write_ogg(&path, &head, 48000, &channels(&tones), |frame, packet| {
+            let len = unsafe {
+                ffi::opus_multistream_encode_float(
+                    encoder,
+                    frame.as_ptr(),
+                    OPUS_FRAME as i32,
+                    packet.as_mut_ptr(),
+                    packet.len() as i32,
+                )
+            };
+            check(len).unwrap();
+            len as usize
+        });
+        unsafe { ffi::opus_multistream_encoder_destroy(encoder) };
+
+        let samples = decode(&path);
+        // Left gets L and C at -3 dB, right only C, all over 1 + 2 / sqrt(2)
+        let total = 1.0 + 2.0 * FRAC_1_SQRT_2;
+        let left = 0.5 / total;
+        let centre = 0.4 * FRAC_1_SQRT_2 / total;
+        assert_level(
+            rms(&samples, 0),
+            ((left * left + centre * centre) / 2.0).sqrt(),
+        );
+        assert_level(rms(&samples, 1), centre * FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn encoder_ends_on_the_last_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mix.opus");
+        let frames = FRAMES;
+        let mut encoder = Box::new(OpusEncoder::create(&path, 48000).unwrap());
+        for chunk in sine(frames, 2, 440.0).chunks(1234) {
+            encoder.write(chunk).unwrap();
+        }
+        encoder.finish().unwrap();
+
+        let mut reader = PacketReader::new(BufReader::new(File::open(&path).unwrap()));
+        let head = reader.read_packet_expected().unwrap();
+        let header = Header::parse(&head.data).unwrap();
+        assert_eq!(header.channels, 2);
+        let mut last = None;
+        while let Some(packet) = reader.read_packet().unwrap() {
+            last = Some(packet);
+        }
+        let last = last.unwrap();
+        assert!(last.last_in_stream());
+        assert_eq!(last.absgp_page(), header.pre_skip + frames as u64);
+
+        let source = OpusSource::ogg(BufReader::new(File::open(&path).unwrap())).unwrap();
+        assert_eq!(source.sample_rate(), 48000);
+        assert_eq!(source.count(), frames * 2);
+    }
+}
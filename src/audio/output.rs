@@ -0,0 +1,183 @@
+use super::device::{self, OutputStream, StreamSettings};
+use super::encoder::{self, Encoder};
+use crate::config::Backend;
+use anyhow::Result;
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Used when no rate is configured, the one Opus recordings need
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+// The virtual outputs pull the mix in 10 ms blocks
+const BLOCKS_PER_SECOND: u32 = 100;
+
+/// Where the engine's mix goes: a sound card, nowhere, or a file.
+pub trait Output {
+    /// Everything added here is played.
+    fn mixer(&self) -> &Arc<DynamicMixerController<f32>>;
+    fn sample_rate(&self) -> u32;
+    /// Why the output has stopped playing, if it has.
+    fn failure(&mut self, _dt: Duration) -> Option<String> {
+        None
+    }
+}
+
+impl Output for OutputStream {
+    fn mixer(&self) -> &Arc<DynamicMixerController<f32>> {
+        &self.mixer
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn failure(&mut self, dt: Duration) -> Option<String> {
+        OutputStream::failure(self, dt)
+    }
+}
+
+/// Opens the output `backend` names. Host, device and settings only matter
+/// for a sound card.
+pub fn open(
+    backend: &Backend,
+    host: Option<&str>,
+    device: Option<&str>,
+    settings: StreamSettings,
+) -> Result<Box<dyn Output>> {
+    let sample_rate = settings.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE);
+    Ok(match backend {
+        Backend::Cpal => Box::new(device::open_stream(host, device, settings)?),
+        Backend::Null => {
+            log::info!("Playing to the null output at {} Hz", sample_rate);
+            Box::new(NullOutput::start(sample_rate, None))
+        }
+        Backend::Record(path) => {
+            let encoder = encoder::create(path, sample_rate)?;
+            log::info!("Recording the mix to '{}'", path.display());
+            Box::new(NullOutput::start(sample_rate, Some(encoder)))
+        }
+    })
+}
+
+/// Plays without a sound card, consuming the mix in real time so sounds,
+/// fades and timers run exactly as they would on a device. With an encoder
+/// the mix is recorded, and the file is completed when the output is dropped.
+pub struct NullOutput {
+    mixer: Arc<DynamicMixerController<f32>>,
+    sample_rate: u32,
+    running: Arc<AtomicBool>,
+    clock: Option<JoinHandle<()>>,
+}
+
+impl NullOutput {
+    pub fn start(sample_rate: u32, encoder: Option<Box<dyn Encoder>>) -> Self {
+        let (mixer, mix) = dynamic_mixer::mixer::<f32>(2, sample_rate);
+        let running = Arc::new(AtomicBool::new(true));
+        let clock = {
+            let running = running.clone();
+            std::thread::spawn(move || run_clock(mix, sample_rate, encoder, &running))
+        };
+        Self {
+            mixer,
+            sample_rate,
+            running,
+            clock: Some(clock),
+        }
+    }
+}
+
+impl Output for NullOutput {
+    fn mixer(&self) -> &Arc<DynamicMixerController<f32>> {
+        &self.mixer
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+impl Drop for NullOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(clock) = self.clock.take() {
+            let _ = clock.join();
+        }
+    }
+}
+
+/// Renders the mix only when asked, with no thread and no clock, so tests
+/// can step the engine and look at exactly what it played. Clones pull from
+/// the same mix.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct PullOutput {
+    mixer: Arc<DynamicMixerController<f32>>,
+    sample_rate: u32,
+    mix: Arc<std::sync::Mutex<DynamicMixer<f32>>>,
+}
+
+#[cfg(test)]
+impl PullOutput {
+    pub fn new(sample_rate: u32) -> Self {
+        let (mixer, mix) = dynamic_mixer::mixer::<f32>(2, sample_rate);
+        Self {
+            mixer,
+            sample_rate,
+            mix: Arc::new(std::sync::Mutex::new(mix)),
+        }
+    }
+
+    /// The next `frames` frames of the mix, in interleaved stereo.
+    pub fn pull(&self, frames: usize) -> Vec<f32> {
+        let mut mix = self.mix.lock().unwrap();
+        mix.by_ref().take(frames * 2).collect()
+    }
+}
+
+#[cfg(test)]
+impl Output for PullOutput {
+    fn mixer(&self) -> &Arc<DynamicMixerController<f32>> {
+        &self.mixer
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+// Pulls a block of the mix every 10 ms, timed against the start so the
+// pace doesn't drift
+fn run_clock(
+    mut mix: DynamicMixer<f32>,
+    sample_rate: u32,
+    mut encoder: Option<Box<dyn Encoder>>,
+    running: &AtomicBool,
+) {
+    let block = (sample_rate / BLOCKS_PER_SECOND) as usize * 2;
+    let mut buffer = Vec::with_capacity(block);
+    let start = Instant::now();
+    let mut blocks = 0;
+    while running.load(Ordering::Relaxed) {
+        buffer.clear();
+        buffer.extend(mix.by_ref().take(block));
+        if let Some(recording) = &mut encoder {
+            if let Err(e) = recording.write(&buffer) {
+                log::error!("Recording stopped: {:#}", e);
+                encoder = None;
+            }
+        }
+
+        blocks += 1;
+        let due = start + Duration::from_secs(blocks) / BLOCKS_PER_SECOND;
+        std::thread::sleep(due.saturating_duration_since(Instant::now()));
+    }
+
+    if let Some(encoder) = encoder {
+        match encoder.finish() {
+            Ok(()) => log::info!("Recording finished"),
+            Err(e) => log::error!("Failed to finish the recording: {:#}", e),
+        }
+    }
+}
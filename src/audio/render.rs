@@ -0,0 +1,128 @@
+use super::breathing::Breath;
+use super::cache::SampleCache;
+use super::effects::EffectsControl;
+use super::encoder::Encoder;
+use super::limiter::Limiter;
+use super::loudness::Normalizer;
+use super::spatial::SpatialControl;
+use super::{sound_source, VolumeRamp};
+use crate::config::AudioConfig;
+use crate::static_data::Sound;
+use anyhow::{Context, Result};
+use rodio::{dynamic_mixer, Sink};
+use std::path::Path;
+use std::time::Duration;
+
+/// Rate files are rendered at, the one Opus requires.
+pub const RENDER_SAMPLE_RATE: u32 = 48000;
+// Volumes are updated every 10 ms, a little finer than the app's ticks
+const BLOCK_FRAMES: usize = RENDER_SAMPLE_RATE as usize / 100;
+
+/// What `render` is busy with, for showing progress.
+pub enum Progress<'a> {
+    /// Measuring a sound's loudness before anything is written.
+    Measuring(&'a str),
+    /// How much of the file has been written.
+    Rendered(Duration),
+}
+
+struct Track {
+    sink: Sink,
+    volume: f32,
+    breath: Option<Breath>,
+}
+
+/// Plays `sounds` at their volumes through the same sources, sinks, mixer and
+/// limiter as the engine, but pulls the mix into `encoder` as fast as it can
+/// be computed instead of handing it to a device. The result fades in and
+/// out over `fade`, and `progress` hears about every loudness measurement
+/// and how far along the file is every second.
+pub fn render(
+    sounds: &[Sound],
+    config: &AudioConfig,
+    loudness_cache: &Path,
+    duration: Duration,
+    fade: Duration,
+    mut encoder: Box<dyn Encoder>,
+    mut progress: impl FnMut(Progress),
+) -> Result<()> {
+    let (mixer, mix) = dynamic_mixer::mixer::<f32>(2, RENDER_SAMPLE_RATE);
+    let mut normalizer = config
+        .normalize_loudness
+        .then(|| Normalizer::new(config.loudness_target, loudness_cache));
+
+    let mut tracks = Vec::new();
+    for sound in sounds {
+        let source = sound_source(
+            sound,
+            SpatialControl::new(sound.spatial),
+            EffectsControl::new(sound.effects),
+            &SampleCache::default(),
+        )
+        .with_context(|| format!("Failed to open '{}'", sound.name))?;
+        let (sink, output) = Sink::new_idle();
+        mixer.add(output);
+        sink.append(source);
+
+        let mut volume = sound.volume_linear;
+        if let Some(normalizer) = &mut normalizer {
+            if sound.generator.is_none() && sound.scatter.is_none() {
+                if !normalizer.is_measured(&sound.file_path) {
+                    progress(Progress::Measuring(&sound.name));
+                }
+                normalizer.track_now(&sound.id, &sound.file_path);
+                volume *= normalizer.gain(&sound.id);
+            }
+        }
+        tracks.push(Track {
+            sink,
+            volume,
+            breath: sound
+                .breathing
+                .is_active()
+                .then(|| Breath::new(sound.breathing)),
+        });
+    }
+
+    let mut output = Limiter::new(mix, config.limiter);
+    let block = Duration::from_secs_f64(BLOCK_FRAMES as f64 / RENDER_SAMPLE_RATE as f64);
+    let total_frames = (duration.as_secs_f64() * RENDER_SAMPLE_RATE as f64) as u64;
+    // A fade can't take longer than half the file
+    let fade = fade.min(duration / 2);
+    let fade_out_at = duration - fade;
+    let mut fade_in = VolumeRamp::new(0.0, 1.0, fade);
+    let mut fade_out = VolumeRamp::new(1.0, 0.0, fade);
+
+    let mut buffer = Vec::with_capacity(BLOCK_FRAMES * 2);
+    let mut written = 0;
+    let mut elapsed = Duration::ZERO;
+    let mut reported = 0;
+    while written < total_frames {
+        let mut envelope = fade_in.value(config.ramp_curve);
+        if elapsed >= fade_out_at {
+            envelope *= fade_out.value(config.ramp_curve);
+            fade_out.advance(block);
+        }
+        fade_in.advance(block);
+        for track in &mut tracks {
+            let breath = track.breath.as_ref().map_or(1.0, Breath::value);
+            track.sink.set_volume(track.volume * envelope * breath);
+            if let Some(breath) = &mut track.breath {
+                breath.advance(block);
+            }
+        }
+
+        let frames = (total_frames - written).min(BLOCK_FRAMES as u64);
+        buffer.clear();
+        buffer.extend(output.by_ref().take(frames as usize * 2));
+        encoder.write(&buffer)?;
+        written += frames;
+        elapsed += block;
+
+        if elapsed.as_secs() > reported {
+            reported = elapsed.as_secs();
+            progress(Progress::Rendered(elapsed));
+        }
+    }
+    encoder.finish()
+}
@@ -0,0 +1,224 @@
+use super::cache::SampleCache;
+use super::generators::Rng;
+use crate::static_data::Scatter;
+use anyhow::Result;
+use rodio::source::UniformSourceIterator;
+use rodio::Source;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 48000;
+const CHANNELS: u16 = 2;
+
+/// Plays random one-shots from a pool with random silence in between, each
+/// at a random level and stereo position.
+///
+/// A helper thread decodes the whole pool into memory, so starting an event
+/// on the audio thread never touches the disk. Events are scheduled once the
+/// first file is ready.
+pub struct ScatterSource {
+    scatter: Scatter,
+    // Each file interleaved at the output rate and layout
+    pool: Vec<(String, Box<[f32]>)>,
+    // Files from the helper, until it has decoded them all
+    decoded: Option<Receiver<(String, Box<[f32]>)>>,
+    rng: Rng,
+    // Frames of silence left before the next event
+    wait: u64,
+    // The pool entry playing and how far into it
+    event: Option<usize>,
+    position: usize,
+    gains: [f32; CHANNELS as usize],
+    channel: usize,
+}
+
+impl ScatterSource {
+    pub fn new(scatter: Scatter, cache: SampleCache) -> Result<Self> {
+        if scatter.files.is_empty() {
+            anyhow::bail!("Scatter sound has no files");
+        }
+        let (tx, decoded) = mpsc::channel();
+        let files = scatter.files.clone();
+        std::thread::spawn(move || {
+            for path in files {
+                match decode(&path, &cache) {
+                    Ok(samples) => {
+                        if tx.send((path, samples)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => log::warn!("Skipping scatter sample '{}': {}", path, e),
+                }
+            }
+        });
+
+        let mut source = Self {
+            scatter,
+            pool: Vec::new(),
+            decoded: Some(decoded),
+            rng: Rng::seeded(),
+            wait: 0,
+            event: None,
+            position: 0,
+            gains: [1.0; CHANNELS as usize],
+            channel: 0,
+        };
+        source.wait = source.random_wait();
+        Ok(source)
+    }
+
+    // Adds the files decoded so far, false once none are left to play
+    fn receive(&mut self) -> bool {
+        let Some(decoded) = &self.decoded else {
+            return !self.pool.is_empty();
+        };
+        loop {
+            match decoded.try_recv() {
+                Ok(file) => self.pool.push(file),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        self.decoded = None;
+        if self.pool.is_empty() {
+            log::error!("Scatter sound has no files that play");
+        }
+        !self.pool.is_empty()
+    }
+
+    /// Blocks until the helper has decoded the whole pool, so tests know
+    /// when the first event starts.
+    #[cfg(test)]
+    fn wait_for_pool(&mut self) {
+        if let Some(decoded) = self.decoded.take() {
+            self.pool.extend(decoded.iter());
+        }
+    }
+
+    // Uniform in 0.0..1.0
+    fn random(&mut self) -> f32 {
+        (self.rng.next_f32() + 1.0) / 2.0
+    }
+
+    fn random_wait(&mut self) -> u64 {
+        let [min, max] = self.scatter.interval;
+        let seconds = min + (max - min) * self.random();
+        (seconds * SAMPLE_RATE as f32) as u64
+    }
+
+    fn start_event(&mut self) {
+        let index = self.rng.next_u32() as usize % self.pool.len();
+        log::debug!("Scatter event: {}", self.pool[index].0);
+
+        let volume = 1.0 - self.scatter.volume_jitter * self.random();
+        let pan = self.scatter.pan_jitter * self.rng.next_f32();
+        // Balance rather than a pan law, the samples are usually stereo already
+        self.gains = [volume * (1.0 - pan).min(1.0), volume * (1.0 + pan).min(1.0)];
+        self.event = Some(index);
+        self.position = 0;
+    }
+}
+
+// Reads a whole one-shot, converted to what the source plays
+fn decode(path: &str, cache: &SampleCache) -> Result<Box<[f32]>> {
+    let source = cache.open(path)?;
+    Ok(UniformSourceIterator::new(source, CHANNELS, SAMPLE_RATE).collect())
+}
+
+impl Iterator for ScatterSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channel = self.channel;
+        if channel == 0 && self.event.is_none() && !self.receive() {
+            return None;
+        }
+        self.channel = (self.channel + 1) % CHANNELS as usize;
+
+        if let Some(index) = self.event {
+            match self.pool[index].1.get(self.position) {
+                Some(&sample) => {
+                    self.position += 1;
+                    return Some(sample * self.gains[channel]);
+                }
+                None => {
+                    self.event = None;
+                    self.wait = self.random_wait();
+                }
+            }
+        }
+
+        // Count silence in whole frames so events start on the left channel
+        if channel == CHANNELS as usize - 1 && !self.pool.is_empty() {
+            if self.wait == 0 {
+                self.start_event();
+            } else {
+                self.wait -= 1;
+            }
+        }
+        Some(0.0)
+    }
+}
+
+impl Source for ScatterSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::decoder;
+    use crate::audio::testing::write_wav;
+
+    fn scatter(files: Vec<String>) -> Scatter {
+        Scatter {
+            files,
+            interval: [0.0, 0.0],
+            volume_jitter: 0.0,
+            pan_jitter: 0.0,
+        }
+    }
+
+    #[test]
+    fn plays_the_decoded_pool_and_skips_broken_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("drop.wav");
+        let ramp = (0..960).flat_map(|i| [i * 20, -i * 20]);
+        write_wav(&good, CHANNELS, SAMPLE_RATE, ramp);
+        let good = good.to_str().unwrap().to_string();
+        let missing = dir.path().join("missing.wav");
+        let missing = missing.to_str().unwrap().to_string();
+
+        let file: Vec<f32> = decoder::open(&good).unwrap().collect();
+        let files = vec![missing.clone(), good];
+        let mut source = ScatterSource::new(scatter(files), SampleCache::default()).unwrap();
+        source.wait_for_pool();
+        // With no wait the first event starts after one silent frame
+        let played: Vec<f32> = source.take(2 + file.len() * 2 + 2).collect();
+        assert_eq!(played[..2], [0.0, 0.0]);
+        assert_eq!(played[2..2 + file.len()], file[..]);
+        assert_eq!(played[2 + file.len()..4 + file.len()], [0.0, 0.0]);
+        assert_eq!(played[4 + file.len()..], file[..]);
+
+        // Nothing to play once the helper gives up on every file
+        let mut source =
+            ScatterSource::new(scatter(vec![missing]), SampleCache::default()).unwrap();
+        source.wait_for_pool();
+        assert_eq!(source.next(), None);
+        assert!(ScatterSource::new(scatter(Vec::new()), SampleCache::default()).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::static_data::Spatial;
+use rodio::source::UniformSourceIterator;
+use rodio::Source;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Share of the distance to the target covered per frame, about 10 ms at 48 kHz
+const SMOOTHING: f32 = 0.002;
+
+/// Pan and width of a playing sound, shared with its audio thread.
+#[derive(Debug)]
+pub struct SpatialControl {
+    pan: AtomicU32,
+    width: AtomicU32,
+}
+
+impl SpatialControl {
+    pub fn new(spatial: Spatial) -> Arc<Self> {
+        let control = Self {
+            pan: AtomicU32::new(0),
+            width: AtomicU32::new(0),
+        };
+        control.set(spatial);
+        Arc::new(control)
+    }
+
+    pub fn set(&self, spatial: Spatial) {
+        self.pan.store(spatial.pan.to_bits(), Ordering::Relaxed);
+        self.width.store(spatial.width.to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> (f32, f32) {
+        (
+            f32::from_bits(self.pan.load(Ordering::Relaxed)),
+            f32::from_bits(self.width.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+type Stereo = UniformSourceIterator<Box<dyn Source<Item = f32> + Send>, f32>;
+
+/// Places a source in the stereo field. Width scales the side signal, so 0.0
+/// is mono and values above 1.0 widen the recording, then pan balances the
+/// result between the speakers.
+pub struct SpatialSource {
+    input: Stereo,
+    control: Arc<SpatialControl>,
+    sample_rate: u32,
+    pan: f32,
+    width: f32,
+    pending_right: Option<f32>,
+}
+
+impl SpatialSource {
+    pub fn new(input: Box<dyn Source<Item = f32> + Send>, control: Arc<SpatialControl>) -> Self {
+        let sample_rate = input.sample_rate();
+        let (pan, width) = control.get();
+        Self {
+            // Mono files are spread to both channels so they can be panned
+            input: UniformSourceIterator::new(input, 2, sample_rate),
+            control,
+            sample_rate,
+            pan,
+            width,
+            pending_right: None,
+        }
+    }
+}
+
+impl Iterator for SpatialSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.pending_right.take() {
+            return Some(right);
+        }
+
+        let left = self.input.next()?;
+        let right = self.input.next().unwrap_or(left);
+
+        // Glide towards new settings so moving them doesn't crackle
+        let (pan, width) = self.control.get();
+        self.pan += (pan - self.pan) * SMOOTHING;
+        self.width += (width - self.width) * SMOOTHING;
+
+        let mid = (left + right) / 2.0;
+        let side = (left - right) / 2.0 * self.width;
+        let left = (mid + side) * (1.0 - self.pan).min(1.0);
+        let right = (mid - side) * (1.0 + self.pan).min(1.0);
+
+        self.pending_right = Some(right);
+        Some(left)
+    }
+}
+
+impl Source for SpatialSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
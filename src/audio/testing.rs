@@ -0,0 +1,63 @@
+use super::encoder::to_i16;
+use super::output::PullOutput;
+use super::Engine;
+use std::path::Path;
+use std::time::Duration;
+
+/// The rate tests run the engine at.
+pub const RATE: u32 = 48000;
+// Stepped the way the app's loop steps it
+const STEP: Duration = Duration::from_millis(10);
+
+/// Writes 16-bit samples, interleaved, to a WAV file.
+pub fn write_wav(
+    path: &Path,
+    channels: u16,
+    sample_rate: u32,
+    samples: impl IntoIterator<Item = i16>,
+) {
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// A tenth of a second of a steady `left` and `right` at [`RATE`], long
+/// enough to loop.
+pub fn write_hum(path: &Path, left: f32, right: f32) {
+    let frame = [to_i16(left), to_i16(right)];
+    write_wav(
+        path,
+        2,
+        RATE,
+        frame.into_iter().cycle().take(RATE as usize / 10 * 2),
+    );
+}
+
+/// Steps the engine for `duration`, giving the left and right level at the
+/// end of every step.
+pub fn run(engine: &mut dyn Engine, output: &PullOutput, duration: Duration) -> Vec<(f32, f32)> {
+    let steps = duration.as_millis() / STEP.as_millis();
+    (0..steps)
+        .map(|_| {
+            engine.update(STEP);
+            let block = output.pull((RATE / 100) as usize);
+            (block[block.len() - 2], block[block.len() - 1])
+        })
+        .collect()
+}
+
+/// Checks the level of a hum of 0.5 played at `gain`.
+pub fn assert_level(level: f32, gain: f32) {
+    assert!(
+        (level - 0.5 * gain).abs() < 1e-3,
+        "level {level}, expected gain {gain}"
+    );
+}
@@ -0,0 +1,292 @@
+use super::opus::{Packet, Packets};
+use anyhow::{Context, Result};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+// Element IDs from the Matroska spec, marker bits included
+const SEGMENT: u32 = 0x1853_8067;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const CLUSTER: u32 = 0x1F43_B675;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const DISCARD_PADDING: u32 = 0x75A2;
+
+// Anything bigger than this isn't an element worth reading into memory
+const MAX_ELEMENT: u64 = 16 << 20;
+
+/// Pulls the Opus packets out of a WebM (Matroska) file, which is what
+/// yt-dlp saves YouTube audio as.
+///
+/// Only the first Opus track is read. The file is walked from front to
+/// back, stepping into the segment and its clusters and skipping the rest,
+/// so files that are still being written or lack an index work too.
+pub struct WebmPackets<R: Read + Seek> {
+    reader: R,
+    track: u64,
+    // Frames of a laced block not handed out yet
+    pending: Vec<Packet>,
+}
+
+impl<R: Read + Seek> WebmPackets<R> {
+    /// Reads up to the track list and returns the Opus track's OpusHead
+    /// along with its packets.
+    pub fn open(mut reader: R) -> Result<(Vec<u8>, Self)> {
+        loop {
+            let (id, size) =
+                read_header(&mut reader)?.context("WebM file ends before its track list")?;
+            match id {
+                SEGMENT => {}
+                CLUSTER => anyhow::bail!("WebM file has no track list before its audio"),
+                TRACKS => {
+                    let tracks = read_body(&mut reader, size)?;
+                    let (track, head) = opus_track(&tracks)?;
+                    let packets = Self {
+                        reader,
+                        track,
+                        pending: Vec::new(),
+                    };
+                    return Ok((head, packets));
+                }
+                _ => skip(&mut reader, size)?,
+            }
+        }
+    }
+
+    // Splits a block into its frames if it belongs to the Opus track
+    fn read_block(&mut self, block: &[u8], padding_ns: i64) -> Result<()> {
+        let mut data = block;
+        let track = read_vint(&mut data).context("Corrupt WebM block")?;
+        if track != self.track {
+            return Ok(());
+        }
+        // A relative timestamp and the flags follow the track number
+        anyhow::ensure!(data.len() >= 3, "Corrupt WebM block");
+        let lacing = (data[2] >> 1) & 0b11;
+        data = &data[3..];
+
+        let sizes = frame_sizes(&mut data, lacing).context("Corrupt WebM block lacing")?;
+        let mut frames = Vec::with_capacity(sizes.len());
+        for size in sizes {
+            anyhow::ensure!(size <= data.len(), "Corrupt WebM block lacing");
+            let (frame, rest) = data.split_at(size);
+            frames.push(Packet {
+                data: frame.to_vec(),
+                discard: 0,
+            });
+            data = rest;
+        }
+        if let Some(last) = frames.last_mut() {
+            last.discard = (padding_ns.max(0) as u64 * 48000).div_ceil(1_000_000_000);
+        }
+        // Handed out from the back
+        frames.reverse();
+        self.pending = frames;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek + Send> Packets for WebmPackets<R> {
+    fn next_packet(&mut self) -> Result<Option<Packet>> {
+        while self.pending.is_empty() {
+            let Some((id, size)) = read_header(&mut self.reader)? else {
+                return Ok(None);
+            };
+            match id {
+                SEGMENT | CLUSTER => {}
+                SIMPLE_BLOCK => {
+                    let block = read_body(&mut self.reader, size)?;
+                    self.read_block(&block, 0)?;
+                }
+                BLOCK_GROUP => {
+                    let group = read_body(&mut self.reader, size)?;
+                    let mut block = None;
+                    let mut padding = 0;
+                    for (id, body) in children(&group)? {
+                        match id {
+                            BLOCK => block = Some(body),
+                            DISCARD_PADDING => padding = read_int(body),
+                            _ => {}
+                        }
+                    }
+                    if let Some(block) = block {
+                        self.read_block(block, padding)?;
+                    }
+                }
+                _ => skip(&mut self.reader, size)?,
+            }
+        }
+        Ok(self.pending.pop())
+    }
+}
+
+// Finds the first Opus track and its OpusHead, which WebM keeps in the
+// track's codec private data
+fn opus_track(tracks: &[u8]) -> Result<(u64, Vec<u8>)> {
+    for (id, entry) in children(tracks)? {
+        if id != TRACK_ENTRY {
+            continue;
+        }
+        let (mut number, mut codec, mut head) = (None, None, None);
+        for (id, body) in children(entry)? {
+            match id {
+                TRACK_NUMBER => number = Some(read_uint(body)),
+                CODEC_ID => codec = Some(body),
+                CODEC_PRIVATE => head = Some(body),
+                _ => {}
+            }
+        }
+        if codec == Some(b"A_OPUS".as_slice()) {
+            let number = number.context("WebM Opus track has no number")?;
+            let head = head.context("WebM Opus track has no OpusHead")?;
+            return Ok((number, head.to_vec()));
+        }
+    }
+    anyhow::bail!("WebM file has no Opus track")
+}
+
+// The sizes of the frames in a block, by its lacing mode
+fn frame_sizes(data: &mut &[u8], lacing: u8) -> Option<Vec<usize>> {
+    if lacing == 0 {
+        return Some(vec![data.len()]);
+    }
+    let count = *data.first()? as usize + 1;
+    *data = &data[1..];
+    let mut sizes = Vec::with_capacity(count);
+    match lacing {
+        // Xiph: runs of 255 add up to each size
+        0b01 => {
+            for _ in 1..count {
+                let mut size = 0;
+                loop {
+                    let byte = *data.first()?;
+                    *data = &data[1..];
+                    size += byte as usize;
+                    if byte != 255 {
+                        break;
+                    }
+                }
+                sizes.push(size);
+            }
+        }
+        // EBML: the first size, then signed differences from the previous one
+        0b11 => {
+            let mut size = read_vint(data)? as i64;
+            sizes.push(size as usize);
+            for _ in 2..count {
+                let length = vint_length(*data.first()?)?;
+                let bias = (1i64 << (7 * length - 1)) - 1;
+                size += read_vint(data)? as i64 - bias;
+                sizes.push(usize::try_from(size).ok()?);
+            }
+        }
+        // Fixed: all the same size
+        _ => return Some(vec![data.len() / count; count]),
+    }
+    let used: usize = sizes.iter().sum();
+    sizes.push(data.len().checked_sub(used)?);
+    Some(sizes)
+}
+
+// Reads an element's ID and size, or `None` at the end of the file. An
+// unknown size comes back as `u64::MAX`.
+fn read_header(reader: &mut impl Read) -> Result<Option<(u32, u64)>> {
+    let mut first = [0];
+    match reader.read_exact(&mut first) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let length = vint_length(first[0]).context("Corrupt WebM element ID")?;
+    anyhow::ensure!(length <= 4, "Corrupt WebM element ID");
+    let mut id = first[0] as u32;
+    for _ in 1..length {
+        id = id << 8 | read_byte(reader)? as u32;
+    }
+
+    let first = read_byte(reader)?;
+    let length = vint_length(first).context("Corrupt WebM element size")?;
+    let mut size = (first as u64) & (0xff >> length);
+    let mut unknown = size == (0xff >> length);
+    for _ in 1..length {
+        let byte = read_byte(reader)?;
+        unknown &= byte == 0xff;
+        size = size << 8 | byte as u64;
+    }
+    Ok(Some((id, if unknown { u64::MAX } else { size })))
+}
+
+fn read_byte(reader: &mut impl Read) -> Result<u8> {
+    let mut byte = [0];
+    reader
+        .read_exact(&mut byte)
+        .context("WebM file is cut short")?;
+    Ok(byte[0])
+}
+
+fn read_body(reader: &mut impl Read, size: u64) -> Result<Vec<u8>> {
+    anyhow::ensure!(size <= MAX_ELEMENT, "WebM element is too large");
+    let mut body = vec![0; size as usize];
+    reader
+        .read_exact(&mut body)
+        .context("WebM file is cut short")?;
+    Ok(body)
+}
+
+fn skip(reader: &mut impl Seek, size: u64) -> Result<()> {
+    anyhow::ensure!(
+        size != u64::MAX,
+        "Can't skip a WebM element of unknown size"
+    );
+    reader.seek(SeekFrom::Current(size as i64))?;
+    Ok(())
+}
+
+// Splits the body of a master element into its children
+fn children(mut body: &[u8]) -> Result<Vec<(u32, &[u8])>> {
+    let mut children = Vec::new();
+    while !body.is_empty() {
+        let (id, size) = read_header(&mut body)?.context("Corrupt WebM element")?;
+        let size = usize::try_from(size)
+            .ok()
+            .filter(|&size| size <= body.len())
+            .context("WebM element overruns its parent")?;
+        children.push((id, &body[..size]));
+        body = &body[size..];
+    }
+    Ok(children)
+}
+
+// Number of bytes in a variable length integer, from the leading zeros of
+// its first byte
+fn vint_length(first: u8) -> Option<u32> {
+    (first != 0).then(|| first.leading_zeros() + 1)
+}
+
+fn read_vint(data: &mut &[u8]) -> Option<u64> {
+    let length = vint_length(*data.first()?)? as usize;
+    let bytes = data.get(..length)?;
+    let value = bytes[1..]
+        .iter()
+        .fold(bytes[0] as u64 & (0xff >> length), |value, &byte| {
+            value << 8 | byte as u64
+        });
+    *data = &data[length..];
+    Some(value)
+}
+
+fn read_uint(body: &[u8]) -> u64 {
+    body.iter().fold(0, |value, &byte| value << 8 | byte as u64)
+}
+
+fn read_int(body: &[u8]) -> i64 {
+    let unsigned = read_uint(body);
+    let bits = body.len() as u32 * 8;
+    if bits == 0 || bits >= 64 {
+        return unsigned as i64;
+    }
+    // Sign-extend from the top bit of the first byte
+    ((unsigned << (64 - bits)) as i64) >> (64 - bits)
+}
@@ -0,0 +1,439 @@
+use crate::app::sleep::format_remaining;
+use crate::audio::encoder;
+use crate::audio::render::{self, Progress};
+use crate::config::{AudioConfig, Backend, Config};
+use crate::ipc::{self, Request, Response, Status};
+use crate::paths::Paths;
+use crate::presets::PresetsConfig;
+use crate::session::Session;
+use crate::static_data::{self, load_catalog, Sound};
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the audio engine in the background without a TUI
+    Daemon,
+    /// Start playing a sound (starts the daemon if needed)
+    Play {
+        /// Sound id or name, see `tanin list`
+        sound: String,
+        /// Volume between 0.0 and 1.0, on the sliders' scale
+        #[arg(short, long, value_parser = parse_volume)]
+        volume: Option<f32>,
+    },
+    /// Stop a sound, or every sound with --all
+    Stop {
+        #[arg(required_unless_present = "all")]
+        sound: Option<String>,
+        #[arg(short, long, conflicts_with = "sound")]
+        all: bool,
+    },
+    /// Load or list presets
+    Preset {
+        #[command(subcommand)]
+        action: PresetAction,
+    },
+    /// List available sounds
+    List {
+        /// Only show sounds from this category
+        #[arg(short, long)]
+        category: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fade out and stop after a while, e.g. `30m`, `1h30m` or `off`
+    Sleep {
+        /// Plain numbers are minutes
+        #[arg(value_parser = parse_sleep)]
+        duration: SleepDuration,
+    },
+    /// Show what the daemon is playing
+    Status {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a preset or the saved session to a .wav or .opus file
+    Render {
+        /// Preset to render, the saved session when left out
+        #[arg(short, long)]
+        preset: Option<String>,
+        /// Length of the file, e.g. `60m` or `1h30m`
+        #[arg(short, long, value_parser = parse_length)]
+        duration: Duration,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Fade in and out over this long
+        #[arg(long, default_value = "5s", value_parser = parse_duration)]
+        fade: Duration,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PresetAction {
+    /// Replace the current mix with a saved preset
+    Load { name: String },
+    /// List saved presets
+    List,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SleepDuration {
+    Off,
+    After(Duration),
+}
+
+fn parse_sleep(s: &str) -> Result<SleepDuration, String> {
+    if s.eq_ignore_ascii_case("off") {
+        return Ok(SleepDuration::Off);
+    }
+    match parse_duration(s)? {
+        d if d.is_zero() => Err("duration must be longer than zero".to_string()),
+        d => Ok(SleepDuration::After(d)),
+    }
+}
+
+/// Parses `90`, `90s`, `45m`, `2h` or combinations like `1h30m`.
+/// A number without a unit is taken as minutes.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if let Ok(minutes) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(minutes * 60));
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("'{}' is not a duration like 30m or 1h", s)),
+        };
+        let value: u64 = number
+            .parse()
+            .map_err(|_| format!("'{}' is not a duration like 30m or 1h", s))?;
+        total += value * unit;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("'{}' is missing a unit after {}", s, number));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+fn parse_length(s: &str) -> Result<Duration, String> {
+    match parse_duration(s)? {
+        d if d.is_zero() => Err("duration must be longer than zero".to_string()),
+        d => Ok(d),
+    }
+}
+
+fn parse_volume(s: &str) -> Result<f32, String> {
+    let volume: f32 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if (0.0..=1.0).contains(&volume) {
+        Ok(volume)
+    } else {
+        Err("volume must be between 0.0 and 1.0".to_string())
+    }
+}
+
+pub fn run(command: Command, backend: Option<Backend>) -> Result<()> {
+    match command {
+        Command::Daemon => crate::daemon::run(backend),
+        Command::Play { sound, volume } => {
+            let sound = find_sound(&sound)?;
+            // Given like a slider position, the daemon takes gain
+            let audio = Config::load(&Paths::standard().config)?.audio;
+            ensure_daemon(backend.as_ref())?;
+            request(Request::Play {
+                id: sound.id,
+                volume: volume.map(|v| audio.slider_to_gain(v)),
+            })
+        }
+        Command::Stop { sound, all } => {
+            if ipc::send(&Paths::standard().socket, &Request::Status).is_err() {
+                // Nothing can be playing without a daemon
+                return Ok(());
+            }
+            let id = if all {
+                None
+            } else {
+                sound.map(|s| find_sound(&s)).transpose()?.map(|s| s.id)
+            };
+            request(Request::Stop { id })
+        }
+        Command::Preset {
+            action: PresetAction::Load { name },
+        } => {
+            let presets = PresetsConfig::load(&Paths::standard().presets)?;
+            let preset = presets
+                .presets
+                .iter()
+                .find(|p| p.name.eq_ignore_ascii_case(&name))
+                .with_context(|| format!("Unknown preset '{}'", name))?;
+            let name = preset.name.clone();
+            ensure_daemon(backend.as_ref())?;
+            request(Request::LoadPreset { name })
+        }
+        Command::Preset {
+            action: PresetAction::List,
+        } => {
+            let presets = PresetsConfig::load(&Paths::standard().presets)?;
+            for preset in &presets.presets {
+                println!("{} ({} sounds)", preset.name, preset.sounds.len());
+            }
+            Ok(())
+        }
+        Command::Sleep { duration } => {
+            let seconds = match duration {
+                SleepDuration::Off => {
+                    if ipc::send(&Paths::standard().socket, &Request::Status).is_err() {
+                        return Ok(());
+                    }
+                    None
+                }
+                SleepDuration::After(d) => {
+                    ensure_daemon(backend.as_ref())?;
+                    Some(d.as_secs())
+                }
+            };
+            request(Request::SetSleepTimer { seconds })
+        }
+        Command::List { category, json } => list(category, json),
+        Command::Status { json } => status(json),
+        Command::Render {
+            preset,
+            duration,
+            output,
+            fade,
+        } => render(preset, duration, &output, fade),
+    }
+}
+
+fn catalog() -> Result<Vec<Sound>> {
+    let paths = Paths::standard();
+    let config = Config::load(&paths.config)?;
+    Ok(load_catalog(&paths, &config.general))
+}
+
+fn find_sound(query: &str) -> Result<Sound> {
+    let sounds = catalog()?;
+    static_data::find_sound(&sounds, query)
+        .map(|i| sounds[i].clone())
+        .with_context(|| format!("Unknown sound '{}', see `tanin list`", query))
+}
+
+fn request(request: Request) -> Result<()> {
+    match ipc::send(&Paths::standard().socket, &request)? {
+        Response::Error { message } => Err(anyhow::anyhow!(message)),
+        _ => Ok(()),
+    }
+}
+
+/// Starts a detached daemon unless one is already answering on the socket,
+/// playing to `backend` when one was asked for.
+fn ensure_daemon(backend: Option<&Backend>) -> Result<()> {
+    let socket = Paths::standard().socket;
+    if ipc::send(&socket, &Request::Status).is_ok() {
+        return Ok(());
+    }
+
+    let exe = std::env::current_exe().context("Could not locate the tanin executable")?;
+    let mut command = std::process::Command::new(exe);
+    match backend {
+        Some(Backend::Null) => {
+            command.arg("--null-audio");
+        }
+        Some(Backend::Record(path)) => {
+            command.arg("--record").arg(path);
+        }
+        Some(Backend::Cpal) | None => {}
+    }
+    command
+        .arg("daemon")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    command.spawn().context("Failed to start tanin daemon")?;
+
+    for _ in 0..100 {
+        std::thread::sleep(Duration::from_millis(50));
+        if ipc::send(&socket, &Request::Status).is_ok() {
+            return Ok(());
+        }
+    }
+    anyhow::bail!("Timed out waiting for the tanin daemon to start")
+}
+
+/// The sounds of a preset, or those the session left playing, with their
+/// volumes and settings as the app would play them.
+fn mix_sounds(preset: Option<&str>) -> Result<Vec<Sound>> {
+    let session = Session::load(&Paths::standard().session)?;
+    let mut sounds = catalog()?;
+    for sound in &mut sounds {
+        if let Some(state) = session.sounds.get(&sound.id) {
+            state.restore(sound);
+        }
+    }
+
+    let Some(name) = preset else {
+        sounds.retain(|s| session.sounds.get(&s.id).is_some_and(|state| state.enabled));
+        anyhow::ensure!(
+            !sounds.is_empty(),
+            "The saved session has no sounds playing"
+        );
+        return Ok(sounds);
+    };
+    let presets = PresetsConfig::load(&Paths::standard().presets)?;
+    let preset = presets
+        .presets
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name))
+        .with_context(|| format!("Unknown preset '{}'", name))?;
+    sounds.retain(|s| preset.sounds.contains_key(&s.id));
+    for sound in &mut sounds {
+        sound.volume_linear = preset.sounds[&sound.id];
+        preset.apply(sound);
+    }
+    anyhow::ensure!(!sounds.is_empty(), "Preset '{}' has no sounds", preset.name);
+    Ok(sounds)
+}
+
+fn render(preset: Option<String>, duration: Duration, output: &Path, fade: Duration) -> Result<()> {
+    let paths = Paths::standard();
+    let config = Config::load(&paths.config)?;
+    let sounds = mix_sounds(preset.as_deref())?;
+    let encoder = encoder::create(output, render::RENDER_SAMPLE_RATE)?;
+
+    let names: Vec<&str> = sounds.iter().map(|s| s.name.as_str()).collect();
+    eprintln!("Rendering {} to {}", names.join(", "), output.display());
+    let total = format_remaining(duration);
+    render::render(
+        &sounds,
+        &config.audio,
+        &paths.loudness,
+        duration,
+        fade,
+        encoder,
+        |progress| match progress {
+            Progress::Measuring(name) => eprintln!("Measuring the loudness of {}", name),
+            Progress::Rendered(elapsed) => {
+                eprint!("\r{} / {}", format_remaining(elapsed), total)
+            }
+        },
+    )?;
+    eprintln!("\rDone, {} written to {}", total, output.display());
+    Ok(())
+}
+
+fn list(category: Option<String>, json: bool) -> Result<()> {
+    let sounds: Vec<Sound> = catalog()?
+        .into_iter()
+        .filter(|s| {
+            category
+                .as_ref()
+                .map_or(true, |c| s.category.eq_ignore_ascii_case(c))
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&sounds)?);
+        return Ok(());
+    }
+
+    let mut current_category = None;
+    for sound in &sounds {
+        if current_category != Some(&sound.category) {
+            println!("{}", sound.category);
+            current_category = Some(&sound.category);
+        }
+        println!("  {:<28} {} {}", sound.id, sound.icon, sound.name);
+    }
+    Ok(())
+}
+
+fn status(json: bool) -> Result<()> {
+    let paths = Paths::standard();
+    let audio = Config::load(&paths.config)?.audio;
+    let status = match ipc::send(&paths.socket, &Request::Status) {
+        Ok(Response::Status(status)) => Some(as_slider_positions(status, &audio)),
+        Ok(Response::Error { message }) => anyhow::bail!(message),
+        _ => None,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    match status {
+        Some(status) => print_status(&status),
+        None => {
+            // Show what the next start would resume from
+            let session = Session::load(&paths.session)?;
+            println!("Daemon is not running");
+            let mut saved: Vec<_> = session.sounds.iter().filter(|(_, s)| s.enabled).collect();
+            saved.sort_by(|a, b| a.0.cmp(b.0));
+            if !saved.is_empty() {
+                println!("Saved session:");
+                for (id, state) in saved {
+                    let position = audio.gain_to_slider(state.volume);
+                    println!("  {:<28} {:>3}%", id, (position * 100.0) as u32);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Volumes as the TUI's sliders show them, the daemon reports gain.
+fn as_slider_positions(mut status: Status, audio: &AudioConfig) -> Status {
+    status.master_volume = audio.gain_to_slider(status.master_volume);
+    for sound in &mut status.sounds {
+        sound.volume = audio.gain_to_slider(sound.volume);
+    }
+    status
+}
+
+fn print_status(status: &Status) {
+    let muted = if status.muted { " (muted)" } else { "" };
+    println!(
+        "Master volume: {:>3}%{}",
+        (status.master_volume * 100.0) as u32,
+        muted
+    );
+    if let Some(preset) = &status.active_preset {
+        println!("Preset: {}", preset);
+    }
+    if let Some(secs) = status.sleep_remaining {
+        println!(
+            "Sleep timer: {}",
+            format_remaining(Duration::from_secs(secs))
+        );
+    }
+
+    let playing: Vec<_> = status.sounds.iter().filter(|s| s.playing).collect();
+    if playing.is_empty() {
+        println!("Nothing playing");
+    } else {
+        println!("Playing:");
+        for sound in playing {
+            println!("  {:<28} {:>3}%", sound.name, (sound.volume * 100.0) as u32);
+        }
+    }
+}
@@ -1,15 +1,25 @@
 use anyhow::Result;
-use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+// Bumped when an older config needs `Config::migrate`
+pub const CONFIG_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    // Missing from files written before versions existed
+    #[serde(default)]
+    pub version: u32,
     #[serde(default)]
     pub general: GeneralConfig,
     pub audio: AudioConfig,
+    #[serde(default)]
+    pub sleep: SleepConfig,
+    #[serde(default)]
+    pub pomodoro: PomodoroConfig,
     pub sounds: HashMap<String, SoundConfig>,
 }
 
@@ -32,10 +42,185 @@ impl Default for GeneralConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SleepConfig {
+    // How long the mix takes to fade out once the timer runs out
+    pub fade_seconds: u64,
+    pub quit_on_expire: bool,
+}
+
+impl Default for SleepConfig {
+    fn default() -> Self {
+        Self {
+            fade_seconds: 60,
+            quit_on_expire: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    // Preset names, a missing one keeps the current mix playing
+    pub work_preset: Option<String>,
+    pub break_preset: Option<String>,
+    pub work_minutes: u64,
+    pub break_minutes: u64,
+    pub long_break_minutes: u64,
+    // Every nth break is a long one
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_preset: None,
+            break_preset: None,
+            work_minutes: 25,
+            break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioConfig {
-    pub sample_rate: u32,
-    pub buffer_size: u32,
+    // cpal host and output device by name, picked automatically when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    // Asked of the output device, which picks its own when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    // In frames, small for low latency and large for Bluetooth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_size: Option<u32>,
+    // How long a preset switch takes to crossfade to the new mix
+    #[serde(default = "default_crossfade_ms")]
+    pub crossfade_ms: u64,
+    // Volume changes glide over this long instead of jumping
+    #[serde(default = "default_volume_ramp_ms")]
+    pub volume_ramp_ms: u64,
+    #[serde(default)]
+    pub ramp_curve: RampCurve,
+    // How sliders and volume keys map onto gain
+    #[serde(default)]
+    pub volume_scale: VolumeScale,
+    // Span of the `decibel` scale, the bottom of the slider above silence
+    #[serde(default = "default_volume_range_db")]
+    pub volume_range_db: f32,
+    // Soft limiter on the final mix, keeps stacked sounds from clipping
+    #[serde(default = "default_limiter")]
+    pub limiter: bool,
+    // Scale every file towards `loudness_target` (LUFS), measured once and cached
+    #[serde(default)]
+    pub normalize_loudness: bool,
+    #[serde(default = "default_loudness_target")]
+    pub loudness_target: f32,
+    // Megabytes of decoded audio kept in memory, 0 turns the cache off
+    #[serde(default)]
+    pub cache_mb: u32,
+    // Where the mix goes, a sound card unless set
+    #[serde(default, skip_serializing_if = "Backend::is_cpal")]
+    pub backend: Backend,
+}
+
+impl AudioConfig {
+    /// Converts a slider position (0.0..=1.0) to the gain that is stored and played.
+    pub fn slider_to_gain(&self, position: f32) -> f32 {
+        let position = position.clamp(0.0, 1.0);
+        match self.volume_scale {
+            VolumeScale::Linear => position,
+            VolumeScale::Cubic => position.powi(3),
+            VolumeScale::Decibel if position <= 0.0 => 0.0,
+            VolumeScale::Decibel => {
+                let db = (position - 1.0) * self.volume_range_db;
+                10f32.powf(db / 20.0)
+            }
+        }
+    }
+
+    pub fn gain_to_slider(&self, gain: f32) -> f32 {
+        let gain = gain.clamp(0.0, 1.0);
+        match self.volume_scale {
+            VolumeScale::Linear => gain,
+            VolumeScale::Cubic => gain.cbrt(),
+            VolumeScale::Decibel if gain <= 0.0 => 0.0,
+            VolumeScale::Decibel => {
+                let db = 20.0 * gain.log10();
+                (1.0 + db / self.volume_range_db).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+fn default_crossfade_ms() -> u64 {
+    3000
+}
+
+fn default_volume_ramp_ms() -> u64 {
+    120
+}
+
+fn default_volume_range_db() -> f32 {
+    50.0
+}
+
+fn default_limiter() -> bool {
+    true
+}
+
+fn default_loudness_target() -> f32 {
+    -23.0
+}
+
+/// Sessions and presets always store gain, this only changes what the sliders show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VolumeScale {
+    #[default]
+    Linear,
+    Cubic,
+    Decibel,
+}
+
+/// The shape of every fade and volume ramp.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RampCurve {
+    #[default]
+    Linear,
+    EqualPower,
+    Exponential,
+}
+
+/// What plays the mix: the sound card, nothing at all, or a file it is
+/// recorded to. The last two keep tanin working without any audio hardware.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    #[default]
+    Cpal,
+    Null,
+    Record(PathBuf),
+}
+
+impl Backend {
+    fn is_cpal(&self) -> bool {
+        *self == Backend::Cpal
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Cpal => write!(f, "the sound card"),
+            Backend::Null => write!(f, "the null output"),
+            Backend::Record(path) => write!(f, "the recording '{}'", path.display()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,32 +232,67 @@ pub struct SoundConfig {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             general: GeneralConfig::default(),
             audio: AudioConfig {
-                sample_rate: 44100,
-                buffer_size: 100,
+                host: None,
+                device: None,
+                sample_rate: None,
+                buffer_size: None,
+                crossfade_ms: default_crossfade_ms(),
+                volume_ramp_ms: default_volume_ramp_ms(),
+                ramp_curve: RampCurve::default(),
+                volume_scale: VolumeScale::default(),
+                volume_range_db: default_volume_range_db(),
+                limiter: default_limiter(),
+                normalize_loudness: false,
+                loudness_target: default_loudness_target(),
+                cache_mb: 0,
+                backend: Backend::default(),
             },
+            sleep: SleepConfig::default(),
+            pomodoro: PomodoroConfig::default(),
             sounds: HashMap::new(),
         }
     }
 }
 
 impl Config {
-    pub fn load() -> Result<Self> {
-        let path = get_config_path()?;
+    pub fn load(path: &Path) -> Result<Self> {
         if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            let config: Config = toml::from_str(&content)?;
+            let content = fs::read_to_string(path)?;
+            let mut config: Config = toml::from_str(&content)?;
+            if config.version < CONFIG_VERSION {
+                config.migrate();
+                if let Err(e) = config.save(path) {
+                    log::warn!("Failed to save migrated config: {}", e);
+                }
+            }
             Ok(config)
         } else {
             let config = Self::default();
-            config.save()?;
+            config.save(path)?;
             Ok(config)
         }
     }
 
-    pub fn save(&self) -> Result<()> {
-        let path = get_config_path()?;
+    /// Brings a config written by an older version up to date.
+    fn migrate(&mut self) {
+        log::info!(
+            "Migrating config from version {} to {}",
+            self.version,
+            CONFIG_VERSION
+        );
+        if self.version < 1 {
+            // Always written as 44100 and 100 and never used, so they would
+            // only force a rate and a tiny buffer on every device now
+            self.audio.sample_rate = None;
+            self.audio.buffer_size = None;
+        }
+        self.version = CONFIG_VERSION;
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -82,10 +302,30 @@ impl Config {
     }
 }
 
-fn get_config_path() -> Result<PathBuf> {
-    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
-        Ok(proj_dirs.config_dir().join("config.toml"))
-    } else {
-        Ok(PathBuf::from("config.toml"))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_CONFIG: &str = "[audio]\nsample_rate = 44100\nbuffer_size = 100\n\n[sounds]\n";
+
+    #[test]
+    fn unversioned_configs_drop_the_placeholder_device_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, OLD_CONFIG).unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.audio.sample_rate, None);
+        assert_eq!(config.audio.buffer_size, None);
+        // Saved, so settings made afterwards are kept
+        let saved: Config = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.version, CONFIG_VERSION);
+
+        let current = format!("version = {}\n{}", CONFIG_VERSION, OLD_CONFIG);
+        fs::write(&path, current).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.audio.sample_rate, Some(44100));
+        assert_eq!(config.audio.buffer_size, Some(100));
     }
 }
@@ -0,0 +1,64 @@
+use crate::app::App;
+use crate::config::Backend;
+use crate::ipc::Server;
+use crate::paths::Paths;
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_shutdown(_signal: libc::c_int) {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+/// Runs the audio engine without a terminal, controlled through the socket.
+pub fn run(backend: Option<Backend>) -> Result<()> {
+    // Bind first so a second daemon fails before it opens the audio device
+    let paths = Paths::standard();
+    let server = Server::bind(paths.socket.clone())?;
+    let mut app = App::new(paths, false, backend, true)?;
+
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            request_shutdown as *const () as libc::sighandler_t,
+        );
+        libc::signal(
+            libc::SIGTERM,
+            request_shutdown as *const () as libc::sighandler_t,
+        );
+        // Keep playing when the terminal that started us goes away
+        libc::signal(libc::SIGHUP, libc::SIG_IGN);
+    }
+
+    log::info!("Daemon listening on {}", server.path().display());
+    println!("tanin daemon listening on {}", server.path().display());
+
+    let tick_rate = Duration::from_millis(30);
+    let mut last_tick = Instant::now();
+
+    while !app.quitting && !SHUTDOWN.load(Ordering::SeqCst) {
+        let timeout = tick_rate.saturating_sub(last_tick.elapsed());
+        match server.rx.recv_timeout(timeout) {
+            Ok((request, reply)) => {
+                let response = app.handle_request(request);
+                let _ = reply.send(response);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        let dt = now.duration_since(last_tick);
+        if dt >= tick_rate {
+            last_tick = now;
+            app.update(dt);
+        }
+    }
+
+    log::info!("Daemon shutting down");
+    app.save_session();
+    Ok(())
+}
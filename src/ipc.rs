@@ -0,0 +1,353 @@
+use crate::audio::Engine;
+use crate::static_data::{Breathing, Effects, Generator, Sound, Spatial, ToneParams};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+// The control protocol is one JSON object per line in each direction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum Request {
+    Play {
+        id: String,
+        volume: Option<f32>,
+    },
+    // No id stops every sound
+    Stop {
+        id: Option<String>,
+    },
+    SetVolume {
+        id: String,
+        volume: f32,
+    },
+    SetMasterVolume {
+        volume: f32,
+    },
+    // Unmuting restores the master volume from before the mute
+    SetMute {
+        muted: bool,
+    },
+    LoadPreset {
+        name: String,
+    },
+    SetTone {
+        id: String,
+        generator: Generator,
+        tone: ToneParams,
+    },
+    SetSpatial {
+        id: String,
+        spatial: Spatial,
+    },
+    SetEffects {
+        id: String,
+        effects: Effects,
+    },
+    SetBreathing {
+        id: String,
+        breathing: Breathing,
+    },
+    // No host and device goes back to the automatic choice
+    SetOutputDevice {
+        host: Option<String>,
+        device: Option<String>,
+    },
+    // No duration cancels the timer
+    SetSleepTimer {
+        seconds: Option<u64>,
+    },
+    Status,
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum Response {
+    Ok,
+    Status(Status),
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub master_volume: f32,
+    pub muted: bool,
+    pub active_preset: Option<String>,
+    #[serde(default)]
+    pub sleep_remaining: Option<u64>,
+    pub sounds: Vec<SoundStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundStatus {
+    pub id: String,
+    pub name: String,
+    pub category: String,
+    pub volume: f32,
+    pub playing: bool,
+}
+
+/// Sends a single request to the running daemon and waits for its answer.
+pub fn send(socket: &Path, request: &Request) -> Result<Response> {
+    let mut stream = UnixStream::connect(socket)
+        .with_context(|| format!("No tanin daemon listening on {}", socket.display()))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reader = BufReader::new(stream);
+    let mut reply = String::new();
+    reader
+        .read_line(&mut reply)
+        .context("Failed to read reply from daemon")?;
+    serde_json::from_str(&reply).context("Daemon sent an invalid reply")
+}
+
+pub struct Server {
+    path: PathBuf,
+    pub rx: Receiver<(Request, Sender<Response>)>,
+}
+
+impl Server {
+    pub fn bind(path: PathBuf) -> Result<Self> {
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                anyhow::bail!("A tanin daemon is already running on {}", path.display());
+            }
+            // Left behind by a daemon that didn't shut down cleanly
+            fs::remove_file(&path)?;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to bind control socket {}", path.display()))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                let tx = tx.clone();
+                thread::spawn(move || handle_connection(stream, tx));
+            }
+        });
+
+        Ok(Self { path, rx })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn handle_connection(stream: UnixStream, tx: Sender<(Request, Sender<Response>)>) {
+    let reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(e) => {
+            log::error!("Failed to clone control connection: {}", e);
+            return;
+        }
+    };
+    let mut writer = stream;
+
+    for line in reader.lines().map_while(Result::ok) {
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                if tx.send((request, reply_tx)).is_err() {
+                    break;
+                }
+                reply_rx.recv().unwrap_or(Response::Error {
+                    message: "Daemon is shutting down".to_string(),
+                })
+            }
+            Err(e) => Response::Error {
+                message: format!("Invalid request: {}", e),
+            },
+        };
+
+        let Ok(mut out) = serde_json::to_string(&response) else {
+            break;
+        };
+        out.push('\n');
+        if writer.write_all(out.as_bytes()).is_err() {
+            break;
+        }
+    }
+}
+
+/// Drives a daemon's engine from the TUI. Playback state is cached locally and
+/// refreshed periodically so rendering never has to wait on the socket.
+pub struct RemoteEngine {
+    socket: PathBuf,
+    playing: HashSet<String>,
+    since_refresh: Duration,
+}
+
+impl RemoteEngine {
+    pub fn connect(socket: &Path) -> Result<(Self, Status)> {
+        match send(socket, &Request::Status)? {
+            Response::Status(status) => {
+                let engine = Self {
+                    socket: socket.to_path_buf(),
+                    playing: playing_ids(&status),
+                    since_refresh: Duration::ZERO,
+                };
+                Ok((engine, status))
+            }
+            Response::Error { message } => Err(anyhow::anyhow!(message)),
+            Response::Ok => Err(anyhow::anyhow!("Unexpected reply from daemon")),
+        }
+    }
+
+    fn request(&self, request: Request) -> Result<()> {
+        match send(&self.socket, &request)? {
+            Response::Error { message } => Err(anyhow::anyhow!(message)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn playing_ids(status: &Status) -> HashSet<String> {
+    status
+        .sounds
+        .iter()
+        .filter(|s| s.playing)
+        .map(|s| s.id.clone())
+        .collect()
+}
+
+impl Engine for RemoteEngine {
+    fn update(&mut self, dt: Duration) {
+        self.since_refresh += dt;
+        if self.since_refresh < Duration::from_secs(1) {
+            return;
+        }
+        self.since_refresh = Duration::ZERO;
+
+        match send(&self.socket, &Request::Status) {
+            Ok(Response::Status(status)) => self.playing = playing_ids(&status),
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to refresh daemon status: {}", e),
+        }
+    }
+
+    fn play(&mut self, sound: &Sound, volume: f32) -> Result<()> {
+        self.request(Request::Play {
+            id: sound.id.clone(),
+            volume: Some(volume),
+        })?;
+        self.playing.insert(sound.id.clone());
+        Ok(())
+    }
+
+    fn stop(&mut self, id: &str) {
+        if let Err(e) = self.request(Request::Stop {
+            id: Some(id.to_string()),
+        }) {
+            log::error!("Failed to stop '{}' on daemon: {}", id, e);
+        }
+        self.playing.remove(id);
+    }
+
+    fn reload(&mut self, sound: &Sound) -> Result<()> {
+        match (sound.generator, sound.tone) {
+            (Some(generator), Some(tone)) if generator.is_tone() => {
+                self.request(Request::SetTone {
+                    id: sound.id.clone(),
+                    generator,
+                    tone,
+                })
+            }
+            // Other sounds have nothing the daemon doesn't already know
+            _ => Ok(()),
+        }
+    }
+
+    fn set_volume(&mut self, id: &str, volume: f32) {
+        if let Err(e) = self.request(Request::SetVolume {
+            id: id.to_string(),
+            volume,
+        }) {
+            log::error!("Failed to set volume of '{}' on daemon: {}", id, e);
+        }
+    }
+
+    fn set_spatial(&mut self, id: &str, spatial: Spatial) {
+        if let Err(e) = self.request(Request::SetSpatial {
+            id: id.to_string(),
+            spatial,
+        }) {
+            log::error!("Failed to set panning of '{}' on daemon: {}", id, e);
+        }
+    }
+
+    fn set_effects(&mut self, id: &str, effects: Effects) {
+        if let Err(e) = self.request(Request::SetEffects {
+            id: id.to_string(),
+            effects,
+        }) {
+            log::error!("Failed to set effects of '{}' on daemon: {}", id, e);
+        }
+    }
+
+    fn set_breathing(&mut self, id: &str, breathing: Breathing) {
+        if let Err(e) = self.request(Request::SetBreathing {
+            id: id.to_string(),
+            breathing,
+        }) {
+            log::error!("Failed to set breathing of '{}' on daemon: {}", id, e);
+        }
+    }
+
+    fn switch_device(&mut self, host: Option<&str>, device: Option<&str>) -> Result<()> {
+        self.request(Request::SetOutputDevice {
+            host: host.map(str::to_string),
+            device: device.map(str::to_string),
+        })
+    }
+
+    fn set_master_volume(&mut self, volume: f32) {
+        if let Err(e) = self.request(Request::SetMasterVolume { volume }) {
+            log::error!("Failed to set master volume on daemon: {}", e);
+        }
+    }
+
+    fn is_playing(&self, id: &str) -> bool {
+        self.playing.contains(id)
+    }
+
+    fn stop_all(&mut self) {
+        if let Err(e) = self.request(Request::Stop { id: None }) {
+            log::error!("Failed to stop sounds on daemon: {}", e);
+        }
+        self.playing.clear();
+    }
+
+    // The daemon runs its own sleep timer and fades its own engine
+    fn fade_out_master(&mut self, _duration: Duration) {}
+
+    fn cancel_master_fade(&mut self) {}
+
+    fn is_fading_out(&self) -> bool {
+        false
+    }
+}
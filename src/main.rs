@@ -1,6 +1,12 @@
 mod app;
 mod audio;
+mod cli;
 mod config;
+mod daemon;
+mod ipc;
+#[cfg(feature = "mpris")]
+mod mpris;
+mod paths;
 mod presets;
 mod session;
 mod static_data;
@@ -15,11 +21,16 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use log::LevelFilter;
-use ratatui::{backend::CrosstermBackend, Terminal};
+use paths::Paths;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    Terminal,
+};
 use simplelog::{Config, WriteLogger};
 use std::fs::File;
 use std::io;
 use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use std::panic;
@@ -30,10 +41,36 @@ struct Args {
     /// Enable debug logging to tanin.log
     #[arg(short, long)]
     debug: bool,
+
+    /// Don't attach to a running daemon, always play locally
+    #[arg(long)]
+    standalone: bool,
+
+    /// Play without a sound card, discarding the mix
+    #[arg(long, global = true, conflicts_with = "record")]
+    null_audio: bool,
+
+    /// Play without a sound card, recording the mix to a WAV or Opus file
+    #[arg(long, global = true, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+}
+
+impl Args {
+    // The output asked for on the command line, if any
+    fn backend(&self) -> Option<config::Backend> {
+        match &self.record {
+            Some(path) => Some(config::Backend::Record(path.clone())),
+            None => self.null_audio.then_some(config::Backend::Null),
+        }
+    }
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let output = args.backend();
 
     if args.debug {
         let log_file = File::create("tanin.log")?;
@@ -47,7 +84,7 @@ fn main() -> Result<()> {
 
         let _ = WriteLogger::init(LevelFilter::Debug, Config::default(), log_file_clone);
         log::info!("Starting Tanin in debug mode");
-    } else {
+    } else if args.command.is_none() {
         // Redirect stderr to /dev/null to suppress errors in TUI
         if let Ok(dev_null) = File::open("/dev/null") {
             let null_fd = dev_null.as_raw_fd();
@@ -57,6 +94,10 @@ fn main() -> Result<()> {
         }
     }
 
+    if let Some(command) = args.command {
+        return cli::run(command, output);
+    }
+
     // Register panic hook to restore terminal and log panic
     panic::set_hook(Box::new(|info| {
         // Attempt to restore terminal state first so user can see output
@@ -94,7 +135,7 @@ fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create app
-    let mut app = App::new()?;
+    let mut app = App::new(Paths::standard(), !args.standalone, output, true)?;
 
     // Run loop
     let res = run_app(&mut terminal, &mut app);
@@ -118,6 +159,17 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Lays the app out for the terminal's size and draws a frame.
+fn draw<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    let size = terminal.size()?;
+    app.width = size.width;
+    app.height = size.height;
+    app.update_grid_cols();
+
+    terminal.draw(|f| ui::ui(f, app))?;
+    Ok(())
+}
+
 fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
     let mut last_tick = std::time::Instant::now();
     let tick_rate = Duration::from_millis(30);
@@ -128,161 +180,13 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
         last_tick = now;
 
         app.update(dt);
-
-        let size = terminal.size()?;
-        app.width = size.width;
-        app.height = size.height;
-        app.update_grid_cols();
-
-        terminal.draw(|f| ui::ui(f, app))?;
+        draw(terminal, app)?;
 
         let timeout = tick_rate.saturating_sub(now.elapsed());
         if crossterm::event::poll(timeout)? {
             loop {
-                match event::read()? {
-                    Event::Key(key) => {
-                        if app.view == CurrentView::Help {
-                            if key.code == KeyCode::Char('q')
-                                || (key.code == KeyCode::Char('c')
-                                    && key
-                                        .modifiers
-                                        .contains(crossterm::event::KeyModifiers::CONTROL))
-                            {
-                                return Ok(());
-                            }
-                            app.view = CurrentView::Main;
-                        } else if app.preset_input_mode {
-                            match key.code {
-                                KeyCode::Enter => {
-                                    app.confirm_preset_input();
-                                    app.preset_input_mode = false;
-                                    app.preset_input_buffer.clear();
-                                }
-                                KeyCode::Esc => {
-                                    app.preset_input_mode = false;
-                                    app.preset_rename_target = None;
-                                    app.preset_input_buffer.clear();
-                                }
-                                KeyCode::Backspace => {
-                                    app.preset_input_buffer.pop();
-                                }
-                                KeyCode::Char(c) => {
-                                    app.preset_input_buffer.push(c);
-                                }
-                                _ => {}
-                            }
-                        } else if app.search_mode {
-                            match key.code {
-                                KeyCode::Enter => {
-                                    app.search_mode = false;
-                                }
-                                KeyCode::Esc => {
-                                    app.search_mode = false;
-                                    app.search_query.clear();
-                                    app.scroll_into_view();
-                                }
-                                KeyCode::Backspace => {
-                                    app.search_query.pop();
-                                    app.validate_cursor_position();
-                                }
-                                KeyCode::Char(c) => {
-                                    app.search_query.push(c);
-                                    app.validate_cursor_position();
-                                }
-                                _ => {}
-                            }
-                        } else if app.view == CurrentView::Downloads {
-                            if key.code == KeyCode::Tab {
-                                app.view = CurrentView::Main;
-                            } else {
-                                handle_add_sound_keys(app, key);
-                            }
-                        } else {
-                            match key.code {
-                                KeyCode::Char('q') => {
-                                    app.quitting = true;
-                                    return Ok(());
-                                }
-                                KeyCode::Esc => {
-                                    if !app.search_query.is_empty() {
-                                        app.search_query.clear();
-                                        app.scroll_into_view();
-                                    } else {
-                                        app.quitting = true;
-                                        return Ok(());
-                                    }
-                                }
-                                KeyCode::Char('c')
-                                    if key
-                                        .modifiers
-                                        .contains(crossterm::event::KeyModifiers::CONTROL) =>
-                                {
-                                    app.quitting = true;
-                                    return Ok(());
-                                }
-
-                                KeyCode::Tab => {
-                                    app.view = match app.view {
-                                        CurrentView::Main => CurrentView::Presets,
-                                        CurrentView::Presets => {
-                                            if app.yt_dlp_available {
-                                                CurrentView::Downloads
-                                            } else {
-                                                CurrentView::Main
-                                            }
-                                        }
-                                        CurrentView::Downloads => CurrentView::Main,
-                                        _ => CurrentView::Main,
-                                    };
-                                }
-
-                                // Help
-                                KeyCode::Char('?') => app.view = CurrentView::Help,
-
-                                // Add Sound
-                                KeyCode::Char('a') if app.view == CurrentView::Main => {
-                                    if app.yt_dlp_available {
-                                        app.view = CurrentView::Downloads;
-                                        app.add_sound_name.clear();
-                                        app.add_sound_category.clear();
-                                        app.add_sound_url.clear();
-                                        app.add_sound_status.clear();
-                                        app.add_sound_focus_index = 0;
-                                        app.add_sound_suggestion = None;
-                                    }
-                                }
-
-                                // Master Mute
-                                KeyCode::Char('m') => app.toggle_mute(),
-
-                                _ => match app.view {
-                                    CurrentView::Main => handle_main_keys(app, key.code),
-                                    CurrentView::Presets => handle_presets_keys(app, key.code),
-                                    CurrentView::Downloads => handle_add_sound_keys(app, key),
-                                    CurrentView::AssetMissing => match key.code {
-                                        KeyCode::Enter => app.start_asset_download(),
-                                        KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
-                                            app.view = CurrentView::Main
-                                        }
-                                        _ => {}
-                                    },
-                                    CurrentView::DownloadingAssets => {
-                                        if app.asset_download_error.is_some()
-                                            && key.code == KeyCode::Esc
-                                        {
-                                            app.view = CurrentView::Main;
-                                            app.asset_download_error = None;
-                                        }
-                                    }
-                                    _ => {}
-                                },
-                            }
-                        }
-                    }
-                    Event::Mouse(mouse) => {
-                        app.handle_mouse_event(mouse);
-                    }
-                    _ => {}
+                if handle_event(app, event::read()?) {
+                    return Ok(());
                 }
 
                 // Check if there are more events to process immediately
@@ -298,6 +202,181 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
     }
 }
 
+/// Applies one terminal event to the app, returning whether it quits.
+fn handle_event(app: &mut App, event: Event) -> bool {
+    match event {
+        Event::Key(key) => {
+            if app.view == CurrentView::Help {
+                if key.code == KeyCode::Char('q')
+                    || (key.code == KeyCode::Char('c')
+                        && key
+                            .modifiers
+                            .contains(crossterm::event::KeyModifiers::CONTROL))
+                {
+                    return true;
+                }
+                app.view = CurrentView::Main;
+            } else if app.view == CurrentView::Detail {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Enter | KeyCode::Char('e') => app.close_detail(),
+                    KeyCode::Up | KeyCode::Char('k') => app.move_detail_cursor(-1),
+                    KeyCode::Down | KeyCode::Char('j') => app.move_detail_cursor(1),
+                    KeyCode::Left | KeyCode::Char('h') => app.adjust_detail_field(-1),
+                    KeyCode::Right | KeyCode::Char('l') => app.adjust_detail_field(1),
+                    _ => {}
+                }
+            } else if app.view == CurrentView::Devices {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('o') => app.close_device_picker(),
+                    KeyCode::Enter => app.confirm_device_picker(),
+                    KeyCode::Up | KeyCode::Char('k') => app.move_device_cursor(-1),
+                    KeyCode::Down | KeyCode::Char('j') => app.move_device_cursor(1),
+                    _ => {}
+                }
+            } else if app.preset_input_mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        app.confirm_preset_input();
+                        app.preset_input_mode = false;
+                        app.preset_input_buffer.clear();
+                    }
+                    KeyCode::Esc => {
+                        app.preset_input_mode = false;
+                        app.preset_rename_target = None;
+                        app.preset_input_buffer.clear();
+                    }
+                    KeyCode::Backspace => {
+                        app.preset_input_buffer.pop();
+                    }
+                    KeyCode::Char(c) => {
+                        app.preset_input_buffer.push(c);
+                    }
+                    _ => {}
+                }
+            } else if app.search_mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        app.search_mode = false;
+                    }
+                    KeyCode::Esc => {
+                        app.search_mode = false;
+                        app.search_query.clear();
+                        app.scroll_into_view();
+                    }
+                    KeyCode::Backspace => {
+                        app.search_query.pop();
+                        app.validate_cursor_position();
+                    }
+                    KeyCode::Char(c) => {
+                        app.search_query.push(c);
+                        app.validate_cursor_position();
+                    }
+                    _ => {}
+                }
+            } else if app.view == CurrentView::Downloads {
+                if key.code == KeyCode::Tab {
+                    app.view = CurrentView::Main;
+                } else {
+                    handle_add_sound_keys(app, key);
+                }
+            } else {
+                match key.code {
+                    KeyCode::Char('q') => {
+                        app.quitting = true;
+                        return true;
+                    }
+                    KeyCode::Esc => {
+                        if !app.search_query.is_empty() {
+                            app.search_query.clear();
+                            app.scroll_into_view();
+                        } else {
+                            app.quitting = true;
+                            return true;
+                        }
+                    }
+                    KeyCode::Char('c')
+                        if key
+                            .modifiers
+                            .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                    {
+                        app.quitting = true;
+                        return true;
+                    }
+
+                    KeyCode::Tab => {
+                        app.view = match app.view {
+                            CurrentView::Main => CurrentView::Presets,
+                            CurrentView::Presets => {
+                                if app.yt_dlp_available {
+                                    CurrentView::Downloads
+                                } else {
+                                    CurrentView::Main
+                                }
+                            }
+                            CurrentView::Downloads => CurrentView::Main,
+                            _ => CurrentView::Main,
+                        };
+                    }
+
+                    // Help
+                    KeyCode::Char('?') => app.view = CurrentView::Help,
+
+                    // Add Sound
+                    KeyCode::Char('a') if app.view == CurrentView::Main => {
+                        if app.yt_dlp_available {
+                            app.view = CurrentView::Downloads;
+                            app.add_sound_name.clear();
+                            app.add_sound_category.clear();
+                            app.add_sound_url.clear();
+                            app.add_sound_status.clear();
+                            app.add_sound_focus_index = 0;
+                            app.add_sound_suggestion = None;
+                        }
+                    }
+
+                    // Master Mute
+                    KeyCode::Char('m') => app.toggle_mute(),
+
+                    // Sleep Timer
+                    KeyCode::Char('t') => app.cycle_sleep_timer(),
+
+                    // Output Device
+                    KeyCode::Char('o') => app.open_device_picker(),
+
+                    // Pomodoro
+                    KeyCode::Char('p') => app.toggle_pomodoro(),
+                    KeyCode::Char('P') => app.skip_pomodoro_phase(),
+
+                    _ => match app.view {
+                        CurrentView::Main => handle_main_keys(app, key.code),
+                        CurrentView::Presets => handle_presets_keys(app, key.code),
+                        CurrentView::Downloads => handle_add_sound_keys(app, key),
+                        CurrentView::AssetMissing => match key.code {
+                            KeyCode::Enter => app.start_asset_download(),
+                            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                                app.view = CurrentView::Main
+                            }
+                            _ => {}
+                        },
+                        CurrentView::DownloadingAssets => {
+                            if app.asset_download_error.is_some() && key.code == KeyCode::Esc {
+                                app.view = CurrentView::Main;
+                                app.asset_download_error = None;
+                            }
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Event::Mouse(mouse) => {
+            app.handle_mouse_event(mouse);
+        }
+        _ => {}
+    }
+    false
+}
+
 fn handle_main_keys(app: &mut App, code: KeyCode) {
     match code {
         // Navigation
@@ -312,45 +391,24 @@ fn handle_main_keys(app: &mut App, code: KeyCode) {
 
         // Sound Control
         KeyCode::Enter | KeyCode::Char(' ') => app.toggle_current_sound(),
-        KeyCode::Char('+') | KeyCode::Char('=') => {
-            let vol = app
-                .sounds
-                .get(app.cursor_pos)
-                .map(|sound| sound.volume_linear);
-
-            if let Some(v) = vol {
-                app.set_current_volume(v + 0.1);
-            }
-        }
-        KeyCode::Char('-') | KeyCode::Char('_') => {
-            let vol = app
-                .sounds
-                .get(app.cursor_pos)
-                .map(|sound| sound.volume_linear);
-
-            if let Some(v) = vol {
-                app.set_current_volume(v - 0.1);
-            }
-        }
+        KeyCode::Char('+') | KeyCode::Char('=') => app.nudge_current_volume(0.1),
+        KeyCode::Char('-') | KeyCode::Char('_') => app.nudge_current_volume(-0.1),
 
         // Quick Volume
         KeyCode::Char(c) if c.is_ascii_digit() => {
             if let Some(d) = c.to_digit(10) {
-                let vol = if d == 0 { 1.0 } else { d as f32 / 10.0 };
-                app.set_current_volume(vol);
+                let position = if d == 0 { 1.0 } else { d as f32 / 10.0 };
+                app.set_current_volume_position(position);
             }
         }
 
         // Master Volume
-        KeyCode::Char('<') | KeyCode::Char(',') => {
-            app.set_master_volume(app.session.global_volume - 0.1);
-        }
-        KeyCode::Char('>') | KeyCode::Char('.') => {
-            app.set_master_volume(app.session.global_volume + 0.1);
-        }
+        KeyCode::Char('<') | KeyCode::Char(',') => app.nudge_master_volume(-0.1),
+        KeyCode::Char('>') | KeyCode::Char('.') => app.nudge_master_volume(0.1),
 
         // Stop All
         KeyCode::Char('s') => app.stop_all(),
+        KeyCode::Char('e') => app.open_detail(),
 
         _ => {}
     }
@@ -474,3 +532,193 @@ fn handle_add_sound_keys(app: &mut App, key: crossterm::event::KeyEvent) {
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+    use ratatui::backend::TestBackend;
+    use ratatui::text::Span;
+    use std::path::Path;
+
+    const CATALOG: &str = r#"
+[Noise.white]
+generator = "white"
+icon = "W"
+
+[Noise.pink]
+generator = "pink"
+icon = "P"
+
+[Noise.brown]
+generator = "brown"
+icon = "B"
+
+[Tones.focus]
+generator = "binaural"
+icon = "F"
+"#;
+
+    const PRESETS: &str = r#"
+[[presets]]
+name = "Morning"
+sounds = { white = 0.5 }
+
+[[presets]]
+name = "Evening"
+sounds = { pink = 0.3, brown = 0.6 }
+"#;
+
+    /// The app started from `dir` with a small catalog of generated sounds.
+    fn app(dir: &Path) -> App {
+        std::fs::create_dir(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("assets").join("sounds.toml"), CATALOG).unwrap();
+        std::fs::write(dir.join("presets.toml"), PRESETS).unwrap();
+        start(dir)
+    }
+
+    // On the null output, without anything the machine has installed and
+    // without a media player on the session bus
+    fn start(dir: &Path) -> App {
+        App::new(
+            Paths::in_dir(dir),
+            false,
+            Some(config::Backend::Null),
+            false,
+        )
+        .unwrap()
+    }
+
+    fn terminal() -> Terminal<TestBackend> {
+        Terminal::new(TestBackend::new(100, 30)).unwrap()
+    }
+
+    // Dispatched like the TUI does, then drawn with no time passing so
+    // animations and fades hold still
+    fn send(app: &mut App, terminal: &mut Terminal<TestBackend>, event: Event) {
+        assert!(!handle_event(app, event));
+        app.update(Duration::ZERO);
+        draw(terminal, app).unwrap();
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn click(x: u16, y: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: x,
+            row: y,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        let mut screen = String::new();
+        for y in 0..buffer.area.height {
+            let mut line = String::new();
+            // Wide characters cover the cell after them
+            let mut covered = 0;
+            for x in 0..buffer.area.width {
+                if covered > 0 {
+                    covered -= 1;
+                    continue;
+                }
+                let symbol = buffer[(x, y)].symbol();
+                covered = Span::raw(symbol).width().saturating_sub(1);
+                line.push_str(symbol);
+            }
+            screen.push_str(line.trim_end());
+            screen.push('\n');
+        }
+        screen
+    }
+
+    /// Compares the screen with tests/snapshots/`name`.txt, or writes it
+    /// there when `UPDATE_SNAPSHOTS` is set.
+    fn assert_snapshot(name: &str, terminal: &Terminal<TestBackend>) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("snapshots")
+            .join(format!("{}.txt", name));
+        let actual = screen(terminal);
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "No snapshot at {}, run with UPDATE_SNAPSHOTS=1",
+                path.display()
+            )
+        });
+        assert_eq!(actual, expected, "{} differs from its snapshot", name);
+    }
+
+    #[test]
+    fn main_view() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(dir.path());
+        let mut terminal = terminal();
+        draw(&mut terminal, &mut app).unwrap();
+        assert_snapshot("main", &terminal);
+
+        send(&mut app, &mut terminal, key(KeyCode::Char('j')));
+        send(&mut app, &mut terminal, key(KeyCode::Char(' ')));
+        assert_snapshot("main_playing", &terminal);
+    }
+
+    #[test]
+    fn clicks_land_on_what_is_drawn() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(dir.path());
+        let mut terminal = terminal();
+        draw(&mut terminal, &mut app).unwrap();
+
+        // The right end of pink's slider
+        send(&mut app, &mut terminal, click(43, 8));
+        assert_eq!(app.sounds[app.cursor_pos].id, "pink");
+        assert_eq!(app.sounds[app.cursor_pos].volume_linear, 1.0);
+        // Pink's name toggles it instead
+        send(&mut app, &mut terminal, click(36, 7));
+        assert!(app.audio_engine.as_ref().unwrap().is_playing("pink"));
+
+        // The left end of the master slider
+        send(&mut app, &mut terminal, click(12, 28));
+        assert_eq!(app.session.global_volume, 0.0);
+        assert_snapshot("clicked_sliders", &terminal);
+
+        send(&mut app, &mut terminal, click(35, 0));
+        assert!(app.view == CurrentView::Presets);
+        assert_snapshot("presets", &terminal);
+
+        // The first click on a preset picks it, the second loads it
+        send(&mut app, &mut terminal, click(5, 8));
+        assert_eq!(app.preset_cursor_pos, 1);
+        assert!(app.active_preset.is_none());
+        send(&mut app, &mut terminal, click(5, 8));
+        assert_eq!(app.active_preset.as_deref(), Some("Evening"));
+        assert!(app.view == CurrentView::Main);
+        let engine = app.audio_engine.as_ref().unwrap();
+        assert!(engine.is_playing("brown"));
+        assert!(engine.is_playing("pink"));
+        assert_snapshot("preset_loaded", &terminal);
+
+        send(&mut app, &mut terminal, click(35, 0));
+        send(&mut app, &mut terminal, click(24, 0));
+        assert!(app.view == CurrentView::Main);
+    }
+
+    #[test]
+    fn missing_assets_offer_a_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = start(dir.path());
+        let mut terminal = terminal();
+        draw(&mut terminal, &mut app).unwrap();
+        assert!(app.view == CurrentView::AssetMissing);
+        assert_snapshot("assets_missing", &terminal);
+    }
+}
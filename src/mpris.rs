@@ -0,0 +1,395 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use zbus::blocking::{connection, Connection};
+use zbus::interface;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+// There is no real track list, the whole mix is reported as one track
+const TRACK_ID: &str = "/org/tanin/Mix";
+
+pub enum MprisCommand {
+    PlayPause,
+    Play,
+    Pause,
+    Stop,
+    SetVolume(f64),
+    Quit,
+}
+
+/// What desktop widgets see, published by the app on every tick.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MprisState {
+    pub playback_status: &'static str,
+    pub volume: f64,
+    pub title: String,
+    pub artists: Vec<String>,
+}
+
+impl MprisState {
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let entries = [
+            (
+                "mpris:trackid",
+                Value::from(ObjectPath::from_static_str_unchecked(TRACK_ID)),
+            ),
+            ("xesam:title", Value::from(self.title.clone())),
+            ("xesam:artist", Value::from(self.artists.clone())),
+        ];
+        for (key, value) in entries {
+            if let Ok(value) = OwnedValue::try_from(value) {
+                metadata.insert(key.to_string(), value);
+            }
+        }
+        metadata
+    }
+}
+
+struct Root {
+    tx: Sender<MprisCommand>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        let _ = self.tx.send(MprisCommand::Quit);
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Tanin"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct Player {
+    tx: Sender<MprisCommand>,
+    state: Arc<Mutex<MprisState>>,
+}
+
+impl Player {
+    fn state(&self) -> MprisState {
+        self.state.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {}
+
+    fn previous(&self) {}
+
+    fn pause(&self) {
+        let _ = self.tx.send(MprisCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        let _ = self.tx.send(MprisCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        let _ = self.tx.send(MprisCommand::Stop);
+    }
+
+    fn play(&self) {
+        let _ = self.tx.send(MprisCommand::Play);
+    }
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        self.state().playback_status.to_string()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.state().metadata()
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.state().volume
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        let _ = self.tx.send(MprisCommand::SetVolume(volume));
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+        0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+pub struct Mpris {
+    connection: Connection,
+    state: Arc<Mutex<MprisState>>,
+    pub rx: Receiver<MprisCommand>,
+}
+
+impl Mpris {
+    /// Registers on the session bus named by `DBUS_SESSION_BUS_ADDRESS`.
+    pub fn start() -> Result<Self> {
+        Self::start_on(None)
+    }
+
+    /// Registers on the bus at `address`, or the session bus without one.
+    pub fn start_on(address: Option<&str>) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let state = Arc::new(Mutex::new(MprisState::default()));
+
+        let name = "org.mpris.MediaPlayer2.tanin";
+        // A second instance (e.g. a daemon plus a standalone TUI) gets its own name
+        let connection = serve(address, name.to_string(), &tx, &state).or_else(|_| {
+            let instance = format!("{}.instance{}", name, std::process::id());
+            serve(address, instance, &tx, &state)
+        })?;
+
+        log::info!("MPRIS interface registered");
+        Ok(Self {
+            connection,
+            state,
+            rx,
+        })
+    }
+
+    /// Stores the new state and tells listeners about whatever changed.
+    pub fn publish(&self, new_state: MprisState) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if *state == new_state {
+            return;
+        }
+
+        let mut changed: HashMap<&str, Value> = HashMap::new();
+        if state.playback_status != new_state.playback_status {
+            changed.insert("PlaybackStatus", Value::from(new_state.playback_status));
+        }
+        if state.volume != new_state.volume {
+            changed.insert("Volume", Value::from(new_state.volume));
+        }
+        if state.title != new_state.title || state.artists != new_state.artists {
+            changed.insert("Metadata", Value::from(new_state.metadata()));
+        }
+        *state = new_state;
+        drop(state);
+
+        let invalidated: Vec<&str> = Vec::new();
+        if let Err(e) = self.connection.emit_signal(
+            None::<()>,
+            OBJECT_PATH,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+            &(PLAYER_INTERFACE, changed, invalidated),
+        ) {
+            log::warn!("Failed to emit MPRIS PropertiesChanged: {}", e);
+        }
+    }
+}
+
+fn serve(
+    address: Option<&str>,
+    name: String,
+    tx: &Sender<MprisCommand>,
+    state: &Arc<Mutex<MprisState>>,
+) -> zbus::Result<Connection> {
+    let root = Root { tx: tx.clone() };
+    let player = Player {
+        tx: tx.clone(),
+        state: state.clone(),
+    };
+
+    let builder = match address {
+        Some(address) => connection::Builder::address(address)?,
+        None => connection::Builder::session()?,
+    };
+    builder
+        .name(name)?
+        .serve_at(OBJECT_PATH, root)?
+        .serve_at(OBJECT_PATH, player)?
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::time::Duration;
+    use zbus::blocking::proxy::Builder;
+    use zbus::blocking::Proxy;
+    use zbus::proxy::CacheProperties;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    // A private session bus, shut down with the test
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Bus {
+        fn start() -> Option<Self> {
+            let daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut bus = Self {
+                daemon,
+                address: String::new(),
+            };
+            let stdout = bus.daemon.stdout.take()?;
+            BufReader::new(stdout).read_line(&mut bus.address).ok()?;
+            bus.address.truncate(bus.address.trim_end().len());
+            Some(bus)
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn player(connection: &Connection) -> Proxy<'static> {
+        Builder::new(connection)
+            .destination("org.mpris.MediaPlayer2.tanin")
+            .unwrap()
+            .path(OBJECT_PATH)
+            .unwrap()
+            .interface(PLAYER_INTERFACE)
+            .unwrap()
+            // Every read should ask the player
+            .cache_properties(CacheProperties::No)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn player_over_dbus() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("dbus-daemon is not available, skipping");
+            return;
+        };
+        let mpris = Mpris::start_on(Some(&bus.address)).unwrap();
+        let client = connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .build()
+            .unwrap();
+        let player = player(&client);
+
+        player.call_method("PlayPause", &()).unwrap();
+        assert!(matches!(
+            mpris.rx.recv_timeout(TIMEOUT),
+            Ok(MprisCommand::PlayPause)
+        ));
+
+        player.set_property("Volume", 0.25f64).unwrap();
+        match mpris.rx.recv_timeout(TIMEOUT) {
+            Ok(MprisCommand::SetVolume(volume)) => assert_eq!(volume, 0.25),
+            _ => panic!("Volume was not passed on"),
+        }
+
+        mpris.publish(MprisState {
+            playback_status: "Playing",
+            volume: 0.6,
+            title: "Evening".to_string(),
+            artists: vec!["Rain".to_string(), "Fire".to_string()],
+        });
+        let status: String = player.get_property("PlaybackStatus").unwrap();
+        assert_eq!(status, "Playing");
+        let volume: f64 = player.get_property("Volume").unwrap();
+        assert_eq!(volume, 0.6);
+
+        let mut metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").unwrap();
+        let title = String::try_from(metadata.remove("xesam:title").unwrap()).unwrap();
+        assert_eq!(title, "Evening");
+        let artists = Vec::<String>::try_from(metadata.remove("xesam:artist").unwrap()).unwrap();
+        assert_eq!(artists, ["Rain", "Fire"]);
+    }
+}
@@ -0,0 +1,89 @@
+use directories::ProjectDirs;
+#[cfg(test)]
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Where the config, session, presets and custom sounds are read from and
+/// saved to, along with everything else tanin finds on the machine.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub config: PathBuf,
+    pub session: PathBuf,
+    pub presets: PathBuf,
+    pub sounds: PathBuf,
+    // The bundled sounds.toml, the first of these that exists is used
+    pub assets: Vec<PathBuf>,
+    // Measured loudness of files, kept between runs
+    pub loudness: PathBuf,
+    // Finished pomodoro work sessions, one JSON object per line
+    pub pomodoro_log: PathBuf,
+    // Where the daemon listens
+    pub socket: PathBuf,
+    // The yt-dlp program, checked for once at start
+    pub yt_dlp: PathBuf,
+}
+
+impl Paths {
+    /// The platform's config and cache directories, or the working
+    /// directory when there is no home to find them in.
+    pub fn standard() -> Self {
+        // A local copy (dev/portable) wins over downloaded and system assets
+        let mut assets = vec![PathBuf::from("assets/sounds.toml")];
+        let dirs = ProjectDirs::from("com", "tanin", "tanin");
+        if let Some(dirs) = &dirs {
+            assets.push(dirs.data_dir().join("assets").join("sounds.toml"));
+        }
+        assets.push(PathBuf::from("/usr/share/tanin/assets/sounds.toml"));
+
+        let socket = match dirs.as_ref().and_then(|dirs| dirs.runtime_dir()) {
+            Some(runtime_dir) => runtime_dir.join("tanin.sock"),
+            None => {
+                let uid = unsafe { libc::getuid() };
+                std::env::temp_dir().join(format!("tanin-{}.sock", uid))
+            }
+        };
+        let yt_dlp = PathBuf::from("yt-dlp");
+
+        match dirs {
+            Some(dirs) => Self {
+                config: dirs.config_dir().join("config.toml"),
+                session: dirs.cache_dir().join("session.toml"),
+                presets: dirs.config_dir().join("presets.toml"),
+                sounds: dirs.config_dir().join("sounds.toml"),
+                assets,
+                loudness: dirs.cache_dir().join("loudness.toml"),
+                pomodoro_log: dirs.data_dir().join("pomodoro.jsonl"),
+                socket,
+                yt_dlp,
+            },
+            None => Self {
+                config: PathBuf::from("config.toml"),
+                session: PathBuf::from("session.toml"),
+                presets: PathBuf::from("presets.toml"),
+                sounds: PathBuf::from("custom_sounds.toml"),
+                assets,
+                loudness: PathBuf::from("loudness.toml"),
+                pomodoro_log: PathBuf::from("pomodoro.jsonl"),
+                socket,
+                yt_dlp,
+            },
+        }
+    }
+
+    /// Everything in one directory, so nothing depends on the machine.
+    /// There is no yt-dlp unless the directory has one.
+    #[cfg(test)]
+    pub fn in_dir(dir: &Path) -> Self {
+        Self {
+            config: dir.join("config.toml"),
+            session: dir.join("session.toml"),
+            presets: dir.join("presets.toml"),
+            sounds: dir.join("sounds.toml"),
+            assets: vec![dir.join("assets").join("sounds.toml")],
+            loudness: dir.join("loudness.toml"),
+            pomodoro_log: dir.join("pomodoro.jsonl"),
+            socket: dir.join("tanin.sock"),
+            yt_dlp: dir.join("yt-dlp"),
+        }
+    }
+}
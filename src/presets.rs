@@ -1,15 +1,51 @@
+use crate::static_data::{Breathing, Effects, Sound, Spatial};
 use anyhow::Result;
-use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Preset {
     pub name: String,
-    // sound_id -> volume (if present, sound is active at this volume)
+    // sound_id -> gain (if present, sound is active at this volume)
     pub sounds: HashMap<String, f32>,
+    // Pan and width of the sounds that aren't centred as-is
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub spatial: HashMap<String, Spatial>,
+    // Filters of the sounds that have any
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub effects: HashMap<String, Effects>,
+    // Volume drift of the sounds that breathe
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub breathing: HashMap<String, Breathing>,
+}
+
+impl Preset {
+    /// Gives a sound of the preset its pan, filters and breathing, going back
+    /// to the catalog's for the ones the preset leaves out.
+    pub fn apply(&self, sound: &mut Sound) {
+        let id = &sound.id;
+        let defaults = sound.defaults;
+        sound.spatial = self
+            .spatial
+            .get(id)
+            .copied()
+            .unwrap_or(defaults.spatial)
+            .clamped();
+        sound.effects = self
+            .effects
+            .get(id)
+            .copied()
+            .unwrap_or(defaults.effects)
+            .clamped();
+        sound.breathing = self
+            .breathing
+            .get(id)
+            .copied()
+            .unwrap_or(defaults.breathing)
+            .clamped();
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -19,10 +55,9 @@ pub struct PresetsConfig {
 }
 
 impl PresetsConfig {
-    pub fn load() -> Result<Self> {
-        let path = get_presets_path()?;
+    pub fn load(path: &Path) -> Result<Self> {
         if path.exists() {
-            let content = fs::read_to_string(&path)?;
+            let content = fs::read_to_string(path)?;
             let config: PresetsConfig = toml::from_str(&content)?;
             Ok(config)
         } else {
@@ -30,8 +65,7 @@ impl PresetsConfig {
         }
     }
 
-    pub fn save(&self) -> Result<()> {
-        let path = get_presets_path()?;
+    pub fn save(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -41,10 +75,39 @@ impl PresetsConfig {
     }
 }
 
-fn get_presets_path() -> Result<PathBuf> {
-    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
-        Ok(proj_dirs.config_dir().join("presets.toml"))
-    } else {
-        Ok(PathBuf::from("presets.toml"))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::static_data::load_sounds_from_file;
+
+    #[test]
+    fn sounds_left_out_go_back_to_the_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sounds.toml");
+        fs::write(&path, "[Nature.rain]\ngenerator = \"pink\"\npan = 0.5\n").unwrap();
+        let mut sound = load_sounds_from_file(&path).unwrap().remove(0);
+        sound.spatial = Spatial {
+            pan: -1.0,
+            width: 0.0,
+        };
+
+        let mut preset = Preset {
+            name: "Evening".to_string(),
+            sounds: HashMap::from([("rain".to_string(), 0.4)]),
+            spatial: HashMap::new(),
+            effects: HashMap::new(),
+            breathing: HashMap::new(),
+        };
+        preset.apply(&mut sound);
+        assert_eq!(sound.spatial.pan, 0.5);
+        assert_eq!(sound.spatial.width, 1.0);
+
+        let left = Spatial {
+            pan: -0.25,
+            width: 1.0,
+        };
+        preset.spatial.insert("rain".to_string(), left);
+        preset.apply(&mut sound);
+        assert_eq!(sound.spatial, left);
     }
 }
@@ -1,13 +1,17 @@
+use crate::static_data::{Breathing, Effects, Generator, Sound, Spatial, ToneParams};
 use anyhow::Result;
-use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
+    // Gain, not slider position, so changing `audio.volume_scale` keeps loudness
     pub global_volume: f32,
+    // Seconds left on the sleep timer when the app was closed
+    #[serde(default)]
+    pub sleep_timer: Option<u64>,
     pub sounds: HashMap<String, SoundState>,
 }
 
@@ -15,22 +19,48 @@ pub struct Session {
 pub struct SoundState {
     pub enabled: bool,
     pub volume: f32,
+    // Only set for binaural and isochronic sounds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<Generator>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tone: Option<ToneParams>,
+    #[serde(default, skip_serializing_if = "Spatial::is_default")]
+    pub spatial: Spatial,
+    #[serde(default, skip_serializing_if = "Effects::is_default")]
+    pub effects: Effects,
+    #[serde(default, skip_serializing_if = "Breathing::is_default")]
+    pub breathing: Breathing,
+}
+
+impl SoundState {
+    /// Puts a sound back the way the session left it.
+    pub fn restore(&self, sound: &mut Sound) {
+        sound.volume_linear = self.volume;
+        sound.spatial = self.spatial.clamped();
+        sound.effects = self.effects.clamped();
+        sound.breathing = self.breathing.clamped();
+        // Tone settings edited in the detail popup
+        if sound.generator.is_some_and(Generator::is_tone) {
+            sound.generator = self.generator.or(sound.generator);
+            sound.tone = self.tone.or(sound.tone);
+        }
+    }
 }
 
 impl Default for Session {
     fn default() -> Self {
         Self {
             global_volume: 0.5,
+            sleep_timer: None,
             sounds: HashMap::new(),
         }
     }
 }
 
 impl Session {
-    pub fn load() -> Result<Self> {
-        let path = get_session_path()?;
+    pub fn load(path: &Path) -> Result<Self> {
         if path.exists() {
-            let content = fs::read_to_string(&path)?;
+            let content = fs::read_to_string(path)?;
             // If it fails to parse (e.g. empty or corrupted), return default instead of crashing
             // because session state is disposable.
             let session: Session = toml::from_str(&content).unwrap_or_default();
@@ -40,8 +70,7 @@ impl Session {
         }
     }
 
-    pub fn save(&self) -> Result<()> {
-        let path = get_session_path()?;
+    pub fn save(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -50,11 +79,3 @@ impl Session {
         Ok(())
     }
 }
-
-fn get_session_path() -> Result<PathBuf> {
-    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
-        Ok(proj_dirs.cache_dir().join("session.toml"))
-    } else {
-        Ok(PathBuf::from("session.toml"))
-    }
-}
@@ -1,3 +1,5 @@
+use crate::config::GeneralConfig;
+use crate::paths::Paths;
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
@@ -17,10 +19,229 @@ pub struct Sound {
     #[serde(default = "default_icon")]
     pub icon: String,
     pub url: Option<String>,
+    // Synthesized instead of read from `file_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<Generator>,
+    // Parameters of the binaural and isochronic generators
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tone: Option<ToneParams>,
+    #[serde(default)]
+    pub looping: LoopPoints,
+    // One-shots played at random instead of a looping bed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scatter: Option<Scatter>,
+    #[serde(default)]
+    pub spatial: Spatial,
+    #[serde(default)]
+    pub effects: Effects,
+    #[serde(default)]
+    pub breathing: Breathing,
+    // As sounds.toml sets them, for presets that leave them out
+    #[serde(skip)]
+    pub defaults: SoundDefaults,
     #[serde(skip)]
     pub error_state: bool,
 }
 
+/// The pan, filters and breathing a sound has before anything is changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SoundDefaults {
+    pub spatial: Spatial,
+    pub effects: Effects,
+    pub breathing: Breathing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Generator {
+    White,
+    Pink,
+    #[serde(alias = "red")]
+    Brown,
+    Blue,
+    Violet,
+    Binaural,
+    Isochronic,
+}
+
+impl Generator {
+    pub fn is_tone(self) -> bool {
+        matches!(self, Generator::Binaural | Generator::Isochronic)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ToneParams {
+    pub carrier: f32,
+    // Difference between the ears, or the pulse rate for isochronic tones
+    pub beat: f32,
+    #[serde(default)]
+    pub waveform: Waveform,
+}
+
+impl Default for ToneParams {
+    fn default() -> Self {
+        Self {
+            carrier: 200.0,
+            beat: 10.0,
+            waveform: Waveform::default(),
+        }
+    }
+}
+
+/// Where a sound sits between the speakers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Spatial {
+    // -1.0 is fully left, 1.0 fully right
+    pub pan: f32,
+    // 0.0 is mono, 1.0 the recording as-is, 2.0 twice as wide
+    pub width: f32,
+}
+
+impl Default for Spatial {
+    fn default() -> Self {
+        Self {
+            pan: 0.0,
+            width: 1.0,
+        }
+    }
+}
+
+impl Spatial {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Keeps values from files and the daemon socket in range.
+    pub fn clamped(self) -> Self {
+        Self {
+            pan: self.pan.clamp(-1.0, 1.0),
+            width: self.width.clamp(0.0, 2.0),
+        }
+    }
+}
+
+/// Filters and EQ applied to a sound, neutral by default.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Effects {
+    // Cutoffs in Hz, at `LOW_PASS_OFF`/`HIGH_PASS_OFF` the filter is bypassed
+    pub low_pass: f32,
+    pub high_pass: f32,
+    // Shelf and peak gains in dB
+    pub low_db: f32,
+    pub mid_db: f32,
+    pub high_db: f32,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            low_pass: Self::LOW_PASS_OFF,
+            high_pass: Self::HIGH_PASS_OFF,
+            low_db: 0.0,
+            mid_db: 0.0,
+            high_db: 0.0,
+        }
+    }
+}
+
+impl Effects {
+    pub const LOW_PASS_OFF: f32 = 20000.0;
+    pub const HIGH_PASS_OFF: f32 = 20.0;
+    pub const MAX_DB: f32 = 12.0;
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Keeps values from files and the daemon socket in range.
+    pub fn clamped(self) -> Self {
+        Self {
+            low_pass: self.low_pass.clamp(200.0, Self::LOW_PASS_OFF),
+            high_pass: self.high_pass.clamp(Self::HIGH_PASS_OFF, 2000.0),
+            low_db: self.low_db.clamp(-Self::MAX_DB, Self::MAX_DB),
+            mid_db: self.mid_db.clamp(-Self::MAX_DB, Self::MAX_DB),
+            high_db: self.high_db.clamp(-Self::MAX_DB, Self::MAX_DB),
+        }
+    }
+}
+
+/// Slow random drift of a sound's volume below its slider.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Breathing {
+    // How far below the slider the volume may sink, 0.0 (off) to 0.9
+    pub depth: f32,
+    // Average seconds between swells
+    pub period: f32,
+}
+
+impl Default for Breathing {
+    fn default() -> Self {
+        Self {
+            depth: 0.0,
+            period: 120.0,
+        }
+    }
+}
+
+impl Breathing {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.depth > 0.0
+    }
+
+    /// Keeps values from files and the daemon socket in range.
+    pub fn clamped(self) -> Self {
+        Self {
+            depth: self.depth.clamp(0.0, 0.9),
+            period: self.period.clamp(15.0, 600.0),
+        }
+    }
+}
+
+/// A pool of one-shot samples played at random intervals.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scatter {
+    pub files: Vec<String>,
+    // Seconds of silence between events, picked uniformly from this range
+    pub interval: [f32; 2],
+    // How much quieter than the slider an event may randomly be, 0.0..=1.0
+    pub volume_jitter: f32,
+    // How far from the centre an event may randomly be panned, 0.0..=1.0
+    pub pan_jitter: f32,
+}
+
+/// Where a file loops and how long the seam is crossfaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoopPoints {
+    pub crossfade_ms: u64,
+    // Seconds into the file, to trim intros and outros
+    pub start: Option<f32>,
+    pub end: Option<f32>,
+}
+
+impl LoopPoints {
+    /// Whether plain repetition of the whole file is enough.
+    pub fn is_plain(&self) -> bool {
+        self.crossfade_ms == 0 && self.start.is_none() && self.end.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+}
+
 fn default_volume() -> f32 {
     0.5
 }
@@ -38,6 +259,21 @@ struct SoundEntry {
     #[serde(default = "default_icon")]
     pub icon: String,
     pub url: Option<String>,
+    pub generator: Option<Generator>,
+    pub carrier: Option<f32>,
+    pub beat: Option<f32>,
+    pub waveform: Option<Waveform>,
+    pub loop_crossfade_ms: Option<u64>,
+    pub loop_start: Option<f32>,
+    pub loop_end: Option<f32>,
+    pub scatter: Option<Vec<String>>,
+    pub interval: Option<[f32; 2]>,
+    pub volume_jitter: Option<f32>,
+    pub pan_jitter: Option<f32>,
+    pub pan: Option<f32>,
+    pub width: Option<f32>,
+    pub breathing: Option<f32>,
+    pub breathing_period: Option<f32>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -46,41 +282,25 @@ pub enum AssetStatus {
     Missing,
 }
 
-pub fn check_assets() -> AssetStatus {
-    if get_active_assets_path().is_some() {
+pub fn check_assets(assets: &[PathBuf]) -> AssetStatus {
+    if get_active_assets_path(assets).is_some() {
         AssetStatus::Present
     } else {
         AssetStatus::Missing
     }
 }
 
-pub fn get_active_assets_path() -> Option<PathBuf> {
-    // 1. Check local (dev/portable)
-    let local = PathBuf::from("assets/sounds.toml");
-    if local.exists() {
-        return Some(local);
-    }
-
-    // 2. Check user data (downloaded)
-    if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
-        let user = proj_dirs.data_dir().join("assets").join("sounds.toml");
-        if user.exists() {
-            return Some(user);
-        }
-    }
-
-    // 3. Check system (AUR/Global)
-    let system = PathBuf::from("/usr/share/tanin/assets/sounds.toml");
-    if system.exists() {
-        return Some(system);
-    }
-
-    None
+/// The first of the bundled sounds.toml files in `assets` that exists.
+pub fn get_active_assets_path(assets: &[PathBuf]) -> Option<&Path> {
+    assets
+        .iter()
+        .map(PathBuf::as_path)
+        .find(|path| path.exists())
 }
 
-pub fn get_bundled_sounds() -> Vec<Sound> {
-    if let Some(path) = get_active_assets_path() {
-        match load_sounds_from_file(&path) {
+pub fn get_bundled_sounds(assets: &[PathBuf]) -> Vec<Sound> {
+    if let Some(path) = get_active_assets_path(assets) {
+        match load_sounds_from_file(path) {
             Ok(sounds) => sounds,
             Err(e) => {
                 eprintln!(
@@ -96,18 +316,12 @@ pub fn get_bundled_sounds() -> Vec<Sound> {
     }
 }
 
-pub fn load_custom_sounds() -> Vec<Sound> {
-    let path = if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
-        proj_dirs.config_dir().join("sounds.toml")
-    } else {
-        PathBuf::from("custom_sounds.toml")
-    };
-
+pub fn load_custom_sounds(path: &Path) -> Vec<Sound> {
     if !path.exists() {
         return Vec::new();
     }
 
-    match load_sounds_from_file(&path) {
+    match load_sounds_from_file(path) {
         Ok(sounds) => sounds,
         Err(e) => {
             eprintln!(
@@ -119,6 +333,39 @@ pub fn load_custom_sounds() -> Vec<Sound> {
     }
 }
 
+/// The bundled sounds (when enabled) and the custom ones, grouped by
+/// category in the configured order.
+pub fn load_catalog(paths: &Paths, general: &GeneralConfig) -> Vec<Sound> {
+    let mut sounds = Vec::new();
+    if general.enable_bundled_sounds {
+        sounds.extend(get_bundled_sounds(&paths.assets));
+    }
+    sounds.extend(load_custom_sounds(&paths.sounds));
+    sort_sounds(&mut sounds, &general.category_order);
+    sounds
+}
+
+/// Categories listed in `category_order` come first, in that order, the
+/// rest follow by name. Sounds within a category are ordered by id.
+pub fn sort_sounds(sounds: &mut [Sound], category_order: &[String]) {
+    let position = |s: &Sound| category_order.iter().position(|c| c == &s.category);
+    sounds.sort_by(|a, b| match (position(a), position(b)) {
+        (Some(ia), Some(ib)) => ia.cmp(&ib).then_with(|| a.id.cmp(&b.id)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.category.cmp(&b.category).then_with(|| a.id.cmp(&b.id)),
+    });
+}
+
+/// Looks a sound up by id or, failing that, display name, ignoring case.
+pub fn find_sound(sounds: &[Sound], query: &str) -> Option<usize> {
+    let query = query.to_lowercase();
+    sounds
+        .iter()
+        .position(|s| s.id.to_lowercase() == query)
+        .or_else(|| sounds.iter().position(|s| s.name.to_lowercase() == query))
+}
+
 pub fn load_sounds_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Sound>> {
     let path = path.as_ref();
     let content = fs::read_to_string(path).context("Could not read sounds configuration file")?;
@@ -160,28 +407,47 @@ pub fn load_sounds_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Sound>> {
                     format!("{}.ogg", slug)
                 });
 
-                let file_path = if Path::new(&filename).is_absolute() {
-                    filename
-                } else if let Some(base) = &base_path_param {
-                    if Path::new(base).is_absolute() {
-                        Path::new(base)
-                            .join(&filename)
-                            .to_string_lossy()
-                            .to_string()
-                    } else {
-                        // Default behavior: expect 'sounds' dir sibling to toml
-                        config_dir
-                            .join("sounds")
-                            .join(&filename)
-                            .to_string_lossy()
-                            .to_string()
-                    }
+                let resolve = |filename: &str| {
+                    resolve_sound_path(filename, base_path_param.as_deref(), config_dir)
+                };
+
+                let file_path = if entry.generator.is_some() || entry.scatter.is_some() {
+                    String::new()
                 } else {
-                    config_dir
-                        .join("sounds")
-                        .join(&filename)
-                        .to_string_lossy()
-                        .to_string()
+                    resolve(&filename)
+                };
+
+                let scatter = entry.scatter.as_ref().map(|files| {
+                    let [min, max] = entry.interval.unwrap_or([30.0, 120.0]);
+                    Scatter {
+                        files: files.iter().map(|f| resolve(f)).collect(),
+                        interval: [min.max(0.0), max.max(min).max(0.0)],
+                        volume_jitter: entry.volume_jitter.unwrap_or(0.3).clamp(0.0, 1.0),
+                        pan_jitter: entry.pan_jitter.unwrap_or(0.5).clamp(0.0, 1.0),
+                    }
+                });
+
+                let tone = entry.generator.filter(|g| g.is_tone()).map(|_| {
+                    let defaults = ToneParams::default();
+                    ToneParams {
+                        carrier: entry.carrier.unwrap_or(defaults.carrier),
+                        beat: entry.beat.unwrap_or(defaults.beat),
+                        waveform: entry.waveform.unwrap_or(defaults.waveform),
+                    }
+                });
+
+                let defaults = SoundDefaults {
+                    spatial: Spatial {
+                        pan: entry.pan.unwrap_or(0.0),
+                        width: entry.width.unwrap_or(1.0),
+                    }
+                    .clamped(),
+                    effects: Effects::default(),
+                    breathing: Breathing {
+                        depth: entry.breathing.unwrap_or(0.0),
+                        period: entry.breathing_period.unwrap_or(120.0),
+                    }
+                    .clamped(),
                 };
 
                 sounds.push(Sound {
@@ -192,6 +458,18 @@ pub fn load_sounds_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Sound>> {
                     volume_linear: entry.volume,
                     icon: entry.icon,
                     url: entry.url,
+                    generator: entry.generator,
+                    tone,
+                    looping: LoopPoints {
+                        crossfade_ms: entry.loop_crossfade_ms.unwrap_or(0),
+                        start: entry.loop_start,
+                        end: entry.loop_end,
+                    },
+                    scatter,
+                    spatial: defaults.spatial,
+                    effects: defaults.effects,
+                    breathing: defaults.breathing,
+                    defaults,
                     error_state: false,
                 });
             }
@@ -211,25 +489,38 @@ pub fn load_sounds_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Sound>> {
     Ok(sounds)
 }
 
+// Relative files live in a "sounds" directory next to the toml, unless an
+// absolute `base_path` says otherwise
+fn resolve_sound_path(filename: &str, base_path: Option<&str>, config_dir: &Path) -> String {
+    if Path::new(filename).is_absolute() {
+        return filename.to_string();
+    }
+    match base_path {
+        Some(base) if Path::new(base).is_absolute() => {
+            Path::new(base).join(filename).to_string_lossy().to_string()
+        }
+        _ => config_dir
+            .join("sounds")
+            .join(filename)
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
 pub fn add_custom_sound(
+    toml_path: &Path,
     name: &str,
     category: &str,
     file_path: &str,
     icon: &str,
     url: Option<&str>,
 ) -> Result<()> {
-    let toml_path = if let Some(proj_dirs) = ProjectDirs::from("com", "tanin", "tanin") {
-        let config_dir = proj_dirs.config_dir();
-        if !config_dir.exists() {
-            fs::create_dir_all(config_dir)?;
-        }
-        config_dir.join("sounds.toml")
-    } else {
-        PathBuf::from("custom_sounds.toml")
-    };
+    if let Some(config_dir) = toml_path.parent() {
+        fs::create_dir_all(config_dir)?;
+    }
 
     let mut root: toml::Table = if toml_path.exists() {
-        let content = fs::read_to_string(&toml_path)?;
+        let content = fs::read_to_string(toml_path)?;
         toml::from_str(&content).unwrap_or_else(|_| toml::Table::new())
     } else {
         toml::Table::new()
@@ -283,3 +574,32 @@ pub fn download_config() -> Result<Vec<Sound>> {
     // Load and return sounds
     load_sounds_from_file(&toml_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_sounds_join_their_bundled_category() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::in_dir(dir.path());
+        fs::create_dir_all(dir.path().join("assets")).unwrap();
+        fs::write(
+            &paths.assets[0],
+            "[Nature.rain]\n[Nature.wind]\n[Noise.white]\ngenerator = \"white\"\n",
+        )
+        .unwrap();
+        fs::write(&paths.sounds, "[Nature.stream]\nname = \"Brook\"\n").unwrap();
+        let general = GeneralConfig {
+            category_order: vec!["Noise".to_string()],
+            ..GeneralConfig::default()
+        };
+
+        let sounds = load_catalog(&paths, &general);
+        let ids: Vec<&str> = sounds.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["white", "rain", "stream", "wind"]);
+        assert_eq!(find_sound(&sounds, "BROOK"), Some(2));
+        assert_eq!(find_sound(&sounds, "Wind"), Some(3));
+        assert_eq!(find_sound(&sounds, "thunder"), None);
+    }
+}
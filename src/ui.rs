@@ -1,4 +1,6 @@
 pub mod assets;
+pub mod detail;
+pub mod devices;
 pub mod download;
 pub mod footer;
 pub mod header;
@@ -8,33 +10,43 @@ pub mod presets;
 
 use crate::app::{App, CurrentView};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
+use std::rc::Rc;
+
+/// Splits the screen into header, content and footer, or `None` when it is
+/// too small to draw anything but a warning.
+pub fn screen_layout(size: Rect) -> Option<Rc<[Rect]>> {
+    if size.width < 60 || size.height < 16 {
+        return None;
+    }
+    Some(
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3), // Header
+                    Constraint::Min(0),    // Main Content
+                    Constraint::Length(3), // Footer
+                ]
+                .as_ref(),
+            )
+            .split(size),
+    )
+}
 
 pub fn ui(f: &mut Frame, app: &mut App) {
     let size = f.area();
 
-    if size.width < 60 || size.height < 16 {
+    let Some(chunks) = screen_layout(size) else {
         let p = Paragraph::new("Terminal Too Small")
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(p, size);
         return;
-    }
-
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Length(3), // Header
-                Constraint::Min(0),    // Main Content
-                Constraint::Length(3), // Footer
-            ]
-            .as_ref(),
-        )
-        .split(size);
+    };
 
     header::render_header(f, app, chunks[0]);
 
@@ -46,6 +58,14 @@ pub fn ui(f: &mut Frame, app: &mut App) {
             main_view::render_grid(f, app, chunks[1]);
             help::render_help(f, size);
         }
+        CurrentView::Detail => {
+            main_view::render_grid(f, app, chunks[1]);
+            detail::render_detail(f, app, size);
+        }
+        CurrentView::Devices => {
+            main_view::render_grid(f, app, chunks[1]);
+            devices::render_devices(f, app, size);
+        }
         CurrentView::AssetMissing => assets::render_asset_prompt(f, app, chunks[1]),
         CurrentView::DownloadingAssets => assets::render_asset_download(f, app, chunks[1]),
     }
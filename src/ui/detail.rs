@@ -0,0 +1,84 @@
+use crate::app::App;
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+pub fn render_detail(f: &mut Frame, app: &App, area: Rect) {
+    let Some(sound) = app.sounds.get(app.cursor_pos) else {
+        return;
+    };
+
+    let source = match (sound.generator, &sound.scatter) {
+        (Some(generator), _) => format!("{:?} generator", generator),
+        (None, Some(scatter)) => format!("{} scattered samples", scatter.files.len()),
+        (None, None) => sound.file_path.clone(),
+    };
+
+    let mut text = vec![
+        Line::from(vec![
+            Span::styled("Category  ", Style::default().fg(Color::DarkGray)),
+            Span::raw(sound.category.clone()),
+        ]),
+        Line::from(vec![
+            Span::styled("Source    ", Style::default().fg(Color::DarkGray)),
+            Span::raw(source),
+        ]),
+        Line::from(""),
+    ];
+
+    let fields = app.detail_fields();
+    if fields.is_empty() {
+        text.push(Line::from(Span::styled(
+            "No adjustable settings",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for (i, field) in fields.into_iter().enumerate() {
+        let selected = i == app.detail_cursor;
+        let style = if selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let value = if selected {
+            format!("◀ {} ▶", app.detail_value(field))
+        } else {
+            format!("  {}  ", app.detail_value(field))
+        };
+        text.push(Line::from(vec![
+            Span::styled(format!("{:<10}", field.label()), style),
+            Span::styled(value, style),
+        ]));
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "↑↓ select  ←→ adjust  Esc close",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let width = 50.min(area.width);
+    let height = (text.len() as u16 + 2).min(area.height);
+    let area = Rect::new(
+        (area.width - width) / 2,
+        (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} {} ", sound.icon, sound.name))
+        .style(Style::default().bg(Color::Black));
+    let p = Paragraph::new(text).block(block).alignment(Alignment::Left);
+
+    f.render_widget(p, area);
+}
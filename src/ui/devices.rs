@@ -0,0 +1,73 @@
+use crate::app::App;
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+pub fn render_devices(f: &mut Frame, app: &App, area: Rect) {
+    let mut text = Vec::new();
+
+    for (i, device) in app.device_list.iter().enumerate() {
+        let selected = i == app.device_cursor;
+        let style = if selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let mark = if app.is_selected_device(device.as_ref()) {
+            "✓ "
+        } else {
+            "  "
+        };
+        let mut line = vec![Span::styled(mark, Style::default().fg(Color::Green))];
+        match device {
+            Some(device) => {
+                line.push(Span::styled(
+                    format!("{:<6}", device.host),
+                    Style::default().fg(Color::DarkGray),
+                ));
+                line.push(Span::styled(device.name.clone(), style));
+            }
+            None => line.push(Span::styled("Automatic", style)),
+        }
+        text.push(Line::from(line));
+    }
+
+    if let Some(error) = &app.device_error {
+        text.push(Line::from(""));
+        text.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "↑↓ select  Enter switch  Esc close",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let width = 60.min(area.width);
+    let height = (text.len() as u16 + 2).min(area.height);
+    let area = Rect::new(
+        (area.width - width) / 2,
+        (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Output Device ")
+        .style(Style::default().bg(Color::Black));
+    let p = Paragraph::new(text).block(block).alignment(Alignment::Left);
+
+    f.render_widget(p, area);
+}
@@ -1,3 +1,4 @@
+use crate::app::sleep::format_remaining;
 use crate::app::{App, CurrentView};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -7,26 +8,45 @@ use ratatui::{
     Frame,
 };
 
-pub fn render_footer(f: &mut Frame, app: &App, area: Rect) {
-    let block = Block::default()
-        .borders(Borders::TOP)
-        .border_style(Style::default().fg(Color::DarkGray));
-    f.render_widget(block, area);
-
-    let inner_area = Rect::new(area.x, area.y + 1, area.width, 1);
+const MASTER_SLIDER_WIDTH: usize = 12;
+const MASTER_LABEL: &str = "  Master ";
 
-    let mute_status = if app.muted {
+fn mute_status(app: &App) -> Span<'static> {
+    if app.muted {
         Span::styled(
             "🔇 MUTED",
             Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
         )
     } else {
         Span::raw("🔊")
-    };
+    }
+}
+
+/// The row under the footer's border.
+pub fn status_row(area: Rect) -> Rect {
+    Rect::new(area.x, area.y + 1, area.width, 1)
+}
 
-    let vol_slider_width = 12;
+/// Where the master volume slider is drawn; left of it is the mute toggle.
+pub fn master_slider(app: &App, area: Rect) -> Rect {
+    let row = status_row(area);
+    // One column of padding comes first
+    let x = row.x + 1 + mute_status(app).width() as u16 + MASTER_LABEL.len() as u16;
+    Rect::new(x, row.y, MASTER_SLIDER_WIDTH as u16, 1)
+}
+
+pub fn render_footer(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::TOP)
+        .border_style(Style::default().fg(Color::DarkGray));
+    f.render_widget(block, area);
+
+    let inner_area = status_row(area);
+
+    let position = app.slider_position(app.session.global_volume);
+    let vol_slider_width = MASTER_SLIDER_WIDTH;
     let knob_pos = if vol_slider_width > 0 {
-        (app.session.global_volume * (vol_slider_width - 1) as f32).round() as usize
+        (position * (vol_slider_width - 1) as f32).round() as usize
     } else {
         0
     };
@@ -42,13 +62,10 @@ pub fn render_footer(f: &mut Frame, app: &App, area: Rect) {
     }
 
     let master_vol_spans = vec![
-        mute_status,
-        Span::raw("  Master "),
+        mute_status(app),
+        Span::raw(MASTER_LABEL),
         Span::styled(slider, Style::default().fg(Color::Blue)),
-        Span::raw(format!(
-            " {:>3}%",
-            (app.session.global_volume * 100.0) as u32
-        )),
+        Span::raw(format!(" {:>3}%", (position * 100.0).round() as u32)),
     ];
 
     let mut left_content = vec![Span::raw(" ")]; // Padding
@@ -62,6 +79,30 @@ pub fn render_footer(f: &mut Frame, app: &App, area: Rect) {
         ));
     }
 
+    if let Some(timer) = &app.sleep_timer {
+        left_content.push(Span::raw("  │  "));
+        let text = if timer.fading {
+            "⏾ Fading out".to_string()
+        } else {
+            format!("⏾ {}", format_remaining(timer.remaining))
+        };
+        left_content.push(Span::styled(text, Style::default().fg(Color::Magenta)));
+    }
+
+    // A failing stream matters more than a fallback made at startup
+    let audio_error = app
+        .audio_engine
+        .as_ref()
+        .and_then(|engine| engine.output_error())
+        .or(app.audio_error.as_deref());
+    if let Some(error) = audio_error {
+        left_content.push(Span::raw("  │  "));
+        left_content.push(Span::styled(
+            format!("⚠ {}", error),
+            Style::default().fg(Color::Red),
+        ));
+    }
+
     let master_vol = Line::from(left_content);
 
     // Dynamic help text based on view
@@ -74,7 +115,7 @@ pub fn render_footer(f: &mut Frame, app: &App, area: Rect) {
             }
         }
         CurrentView::Downloads => "Enter: Queue Download  Tab: Switch View  q: Quit",
-        _ => "Tab: Presets  SPACE: Toggle  m: Mute  ?: Help  q: Quit",
+        _ => "Tab: Presets  SPACE: Toggle  m: Mute  t: Sleep  ?: Help  q: Quit",
     };
 
     let p_left = Paragraph::new(master_vol).alignment(Alignment::Left);
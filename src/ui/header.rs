@@ -1,4 +1,5 @@
-use crate::app::{App, CurrentView};
+use crate::app::sleep::format_remaining;
+use crate::app::{App, CurrentView, PomodoroPhase};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -6,16 +7,101 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Tabs},
     Frame,
 };
+use std::rc::Rc;
 
-pub fn render_header(f: &mut Frame, app: &App, area: Rect) {
-    let chunks = Layout::default()
+// The title's column on the left
+const TITLE_WIDTH: u16 = 20;
+// Tabs puts a space either side of each title, and a divider between them
+const TAB_PADDING: u16 = 2;
+const TAB_DIVIDER: u16 = 1;
+
+// Right: Status
+fn status_line(app: &App) -> Line<'static> {
+    let active_count = if let Some(engine) = &app.audio_engine {
+        app.sounds
+            .iter()
+            .filter(|s| engine.is_playing(&s.id))
+            .count()
+    } else {
+        0
+    };
+
+    let mut right_spans = vec![];
+
+    if let Some(pomodoro) = &app.pomodoro {
+        let color = match pomodoro.phase {
+            PomodoroPhase::Work => Color::Red,
+            PomodoroPhase::Break | PomodoroPhase::LongBreak => Color::Green,
+        };
+        right_spans.push(Span::styled(
+            format!(
+                "🍅 {} {} ",
+                pomodoro.phase.label(),
+                format_remaining(pomodoro.remaining)
+            ),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if app.attached {
+        right_spans.push(Span::styled("daemon ", Style::default().fg(Color::Cyan)));
+    }
+
+    if active_count > 0 {
+        right_spans.push(Span::styled(
+            format!(" ▶ {} ", active_count),
+            Style::default().bg(Color::Green).fg(Color::White),
+        ));
+        right_spans.push(Span::raw("  "));
+    }
+    right_spans.push(Span::styled("? help", Style::default().fg(Color::DarkGray)));
+
+    Line::from(right_spans)
+}
+
+/// Splits the header into the title, the tabs (or search) and the status.
+pub fn header_layout(app: &App, area: Rect) -> Rc<[Rect]> {
+    // Grow the status area when the pomodoro countdown is showing
+    let right_width = (status_line(app).width() as u16).max(20);
+    Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Length(20),
+            Constraint::Length(TITLE_WIDTH),
             Constraint::Min(0),
-            Constraint::Length(20),
+            Constraint::Length(right_width),
         ])
-        .split(area);
+        .split(area)
+}
+
+fn tabs(app: &App) -> Vec<(&'static str, CurrentView)> {
+    let mut tabs = vec![
+        (" Sounds ", CurrentView::Main),
+        (" Presets ", CurrentView::Presets),
+    ];
+    if app.yt_dlp_available {
+        tabs.push((" Downloads ", CurrentView::Downloads));
+    }
+    tabs
+}
+
+/// The view whose tab is at column `x`, if the tabs are showing.
+pub fn tab_at(app: &App, area: Rect, x: u16) -> Option<CurrentView> {
+    if app.search_mode || !app.search_query.is_empty() {
+        return None;
+    }
+    let mut left = header_layout(app, area)[1].x;
+    for (title, view) in tabs(app) {
+        let right = left + title.len() as u16 + TAB_PADDING;
+        if (left..right).contains(&x) {
+            return Some(view);
+        }
+        left = right + TAB_DIVIDER;
+    }
+    None
+}
+
+pub fn render_header(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = header_layout(app, area);
 
     // Left: Title
     let title = Span::styled(
@@ -43,13 +129,10 @@ pub fn render_header(f: &mut Frame, app: &App, area: Rect) {
             .block(Block::default().borders(Borders::NONE));
         f.render_widget(p, chunks[1]);
     } else {
-        let mut titles = vec![" Sounds ", " Presets "];
-        if app.yt_dlp_available {
-            titles.push(" Downloads ");
-        }
+        let titles: Vec<&str> = tabs(app).into_iter().map(|(title, _)| title).collect();
 
         let selected_tab = match app.view {
-            CurrentView::Main | CurrentView::Help => 0,
+            CurrentView::Main | CurrentView::Help | CurrentView::Detail | CurrentView::Devices => 0,
             CurrentView::Presets => 1,
             CurrentView::Downloads => 2,
             CurrentView::AssetMissing | CurrentView::DownloadingAssets => 0,
@@ -69,27 +152,6 @@ pub fn render_header(f: &mut Frame, app: &App, area: Rect) {
         f.render_widget(tabs, chunks[1]);
     }
 
-    // Right: Status
-    let active_count = if let Some(engine) = &app.audio_engine {
-        app.sounds
-            .iter()
-            .filter(|s| engine.is_playing(&s.id))
-            .count()
-    } else {
-        0
-    };
-
-    let mut right_spans = vec![];
-
-    if active_count > 0 {
-        right_spans.push(Span::styled(
-            format!(" ▶ {} ", active_count),
-            Style::default().bg(Color::Green).fg(Color::White),
-        ));
-        right_spans.push(Span::raw("  "));
-    }
-    right_spans.push(Span::styled("? help", Style::default().fg(Color::DarkGray)));
-
-    let p_right = Paragraph::new(Line::from(right_spans)).alignment(Alignment::Right);
+    let p_right = Paragraph::new(status_line(app)).alignment(Alignment::Right);
     f.render_widget(p_right, chunks[2]);
 }
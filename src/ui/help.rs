@@ -32,6 +32,7 @@ pub fn render_help(f: &mut Frame, area: Rect) {
         Line::from("  < / >           Master Volume"),
         Line::from("  + / -           Volume"),
         Line::from("  s               Stop all"),
+        Line::from("  e               Sound Details (pan, EQ, breathing, tone)"),
         Line::from(""),
         Line::from(Span::styled(
             "Presets View",
@@ -45,6 +46,9 @@ pub fn render_help(f: &mut Frame, area: Rect) {
         Line::from(""),
         Line::from(Span::styled("General", Style::default().fg(Color::Green))),
         Line::from("  m               Mute Master"),
+        Line::from("  t               Sleep Timer (15m steps, then off)"),
+        Line::from("  p / P           Start/Stop Pomodoro / Skip Phase"),
+        Line::from("  o               Output Device"),
         Line::from("  ?               Toggle Help"),
         Line::from("  q               Quit"),
     ];
@@ -1,42 +1,52 @@
 use crate::app::App;
 use crate::static_data::Sound;
 use ratatui::{
-    layout::{Alignment, Rect},
+    layout::{Alignment, Position, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, BorderType, Borders, Paragraph},
     Frame,
 };
 
-pub fn render_grid(f: &mut Frame, app: &App, area: Rect) {
-    let mut current_y: i32 = area.y as i32 - app.grid_scroll as i32;
-    let card_width = 24;
-    let card_height = 5;
-    let col_width = card_width + 2;
-    let cols = app.grid_cols;
+pub const CARD_WIDTH: u16 = 24;
+pub const CARD_HEIGHT: u16 = 5;
+// Columns between cards side by side, and rows between rows of cards
+pub const CARD_GAP: u16 = 2;
+pub const ROW_GAP: u16 = 1;
+// A category title and the blank row under it
+pub const CATEGORY_HEIGHT: u16 = 2;
+// Space left of the first column
+pub const GRID_MARGIN: u16 = 2;
+// Cells of a card that aren't slider: the borders, and the percentage after it
+const SLIDER_INSET: u16 = 10;
 
-    let filtered = app.get_filtered_sounds();
-
-    if filtered.is_empty() {
-        let msg = if app.search_query.is_empty() {
-            "No sounds available.\nAdd custom sounds or check assets."
-        } else {
-            "No sounds match your search."
-        };
-
-        let p = Paragraph::new(msg)
-            .style(Style::default().fg(Color::DarkGray))
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::NONE));
-
-        // Center vertically in the area
-        let center_y = area.height / 2;
-        let msg_area = Rect::new(area.x, area.y + center_y.saturating_sub(1), area.width, 2);
+/// Where the grid's category titles and cards land on screen, so drawing
+/// and mouse hit-testing agree.
+pub struct GridLayout {
+    pub headers: Vec<(String, Rect)>,
+    // Sound index and its card, cut off at the bottom of the area
+    pub cards: Vec<(usize, Rect)>,
+}
 
-        f.render_widget(p, msg_area);
-        return;
+impl GridLayout {
+    pub fn card_at(&self, x: u16, y: u16) -> Option<(usize, Rect)> {
+        self.cards
+            .iter()
+            .copied()
+            .find(|(_, card)| card.contains(Position::new(x, y)))
     }
+}
+
+pub fn grid_layout(app: &App, area: Rect) -> GridLayout {
+    let mut layout = GridLayout {
+        headers: Vec::new(),
+        cards: Vec::new(),
+    };
+    let mut current_y: i32 = area.y as i32 - app.grid_scroll as i32;
+    let col_width = CARD_WIDTH + CARD_GAP;
+    let cols = app.grid_cols;
 
+    let filtered = app.get_filtered_sounds();
     let mut categories: Vec<String> = filtered.iter().map(|(_, s)| s.category.clone()).collect();
     categories.dedup();
 
@@ -45,21 +55,21 @@ pub fn render_grid(f: &mut Frame, app: &App, area: Rect) {
             break;
         }
 
-        // Render header if visible
-        if current_y + 1 >= area.top() as i32 && current_y < area.bottom() as i32 {
-            let header_rect = Rect::new(area.x + 2, current_y as u16, area.width - 4, 1);
-            f.render_widget(
-                Paragraph::new(format!("─── {} ───", category))
-                    .style(Style::default().fg(Color::DarkGray)),
-                header_rect,
+        if current_y >= area.top() as i32 && current_y < area.bottom() as i32 {
+            let header_rect = Rect::new(
+                area.x + GRID_MARGIN,
+                current_y as u16,
+                area.width.saturating_sub(GRID_MARGIN * 2),
+                1,
             );
+            layout.headers.push((category.clone(), header_rect));
         }
-        current_y += 2;
+        current_y += CATEGORY_HEIGHT as i32;
 
-        let sounds_in_cat: Vec<(usize, &Sound)> = filtered
+        let sounds_in_cat: Vec<usize> = filtered
             .iter()
             .filter(|(_, s)| s.category == category)
-            .map(|(i, s)| (*i, *s))
+            .map(|(i, _)| *i)
             .collect();
 
         for chunk in sounds_in_cat.chunks(cols as usize) {
@@ -67,30 +77,79 @@ pub fn render_grid(f: &mut Frame, app: &App, area: Rect) {
                 break;
             }
 
-            if current_y + card_height as i32 > area.top() as i32 {
-                for (i, (global_idx, sound)) in chunk.iter().enumerate() {
-                    let x = area.x + 2 + (i as u16 * col_width);
-                    if x + card_width > area.right() {
-                        continue;
-                    }
-
-                    let card_y = current_y;
-                    if card_y < 0 {
+            // Cards scrolled partly above the area are left out entirely
+            if current_y >= area.top() as i32 {
+                let card_y = current_y as u16;
+                let visible_height = CARD_HEIGHT.min(area.bottom().saturating_sub(card_y));
+                for (i, global_idx) in chunk.iter().enumerate() {
+                    let x = area.x + GRID_MARGIN + i as u16 * col_width;
+                    if x + CARD_WIDTH > area.right() || visible_height == 0 {
                         continue;
                     }
-
-                    let visible_height =
-                        std::cmp::min(card_height, (area.bottom() as i32 - card_y).max(0) as u16);
-
-                    if visible_height > 0 && card_y >= area.top() as i32 {
-                        let rect = Rect::new(x, card_y as u16, card_width, visible_height);
-                        render_card(f, app, *global_idx, sound, rect);
-                    }
+                    let rect = Rect::new(x, card_y, CARD_WIDTH, visible_height);
+                    layout.cards.push((*global_idx, rect));
                 }
             }
-            current_y += card_height as i32 + 1;
+            current_y += (CARD_HEIGHT + ROW_GAP) as i32;
         }
     }
+    layout
+}
+
+/// The row of a card its volume slider is on, from the left border to the
+/// right one, or `None` when the card is cut off above it.
+pub fn slider_row(card: Rect) -> Option<Rect> {
+    (card.height >= CARD_HEIGHT).then(|| Rect::new(card.x + 1, card.y + 3, card.width - 2, 1))
+}
+
+// The slider's cells within its row, centred with the percentage after them
+fn slider_cells(row: Rect) -> Rect {
+    // The row leaves out the card's two borders
+    let width = (row.width + 2).saturating_sub(SLIDER_INSET);
+    let line_width = width + 5;
+    Rect::new(row.x + (row.width - line_width) / 2, row.y, width, 1)
+}
+
+/// The slider position a click at column `x` of a card's slider row sets.
+pub fn slider_position_at(row: Rect, x: u16) -> f32 {
+    let cells = slider_cells(row);
+    (x.saturating_sub(cells.x) as f32 / (cells.width - 1) as f32).clamp(0.0, 1.0)
+}
+
+pub fn render_grid(f: &mut Frame, app: &App, area: Rect) {
+    let filtered = app.get_filtered_sounds();
+
+    if filtered.is_empty() {
+        let msg = if app.search_query.is_empty() {
+            "No sounds available.\nAdd custom sounds or check assets."
+        } else {
+            "No sounds match your search."
+        };
+
+        let p = Paragraph::new(msg)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::NONE));
+
+        // Center vertically in the area
+        let center_y = area.height / 2;
+        let msg_area = Rect::new(area.x, area.y + center_y.saturating_sub(1), area.width, 2);
+
+        f.render_widget(p, msg_area);
+        return;
+    }
+
+    let layout = grid_layout(app, area);
+    for (category, rect) in layout.headers {
+        f.render_widget(
+            Paragraph::new(format!("─── {} ───", category))
+                .style(Style::default().fg(Color::DarkGray)),
+            rect,
+        );
+    }
+    for (idx, rect) in layout.cards {
+        render_card(f, app, idx, &app.sounds[idx], rect);
+    }
 }
 
 fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect) {
@@ -162,18 +221,32 @@ fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect)
         sound.name.clone()
     };
 
-    let vol_width = (area.width as usize).saturating_sub(10);
-    let knob_pos = if vol_width > 0 {
-        (sound.volume_linear * (vol_width - 1) as f32).round() as usize
-    } else {
-        0
+    let position = app.slider_position(sound.volume_linear);
+    // Breathing sounds fill the bar only up to where the volume has drifted,
+    // the knob stays on the slider value
+    let modulation = match &app.audio_engine {
+        Some(engine) if playing => engine.modulation(&sound.id),
+        _ => 1.0,
+    };
+    let live_position = app.slider_position(sound.volume_linear * modulation);
+    let vol_width = area.width.saturating_sub(SLIDER_INSET) as usize;
+    let to_cell = |position: f32| {
+        if vol_width > 0 {
+            (position * (vol_width - 1) as f32).round() as usize
+        } else {
+            0
+        }
     };
+    let knob_pos = to_cell(position);
+    let live_pos = to_cell(live_position);
     let mut slider = String::new();
     for i in 0..vol_width {
         if i == knob_pos {
             slider.push('●');
-        } else if i < knob_pos {
+        } else if i < live_pos {
             slider.push('━');
+        } else if i < knob_pos {
+            slider.push('┄');
         } else {
             slider.push('─');
         }
@@ -182,23 +255,6 @@ fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect)
     let content = vec![
         Line::from(Span::raw(icon)),
         Line::from(Span::styled(title_text, title_style)),
-        Line::from(vec![
-            Span::styled(
-                slider,
-                Style::default().fg(if sound.error_state {
-                    Color::Red
-                } else if playing {
-                    if selected {
-                        Color::Black
-                    } else {
-                        Color::Green
-                    }
-                } else {
-                    Color::Blue
-                }),
-            ),
-            Span::raw(format!(" {:>3}%", (sound.volume_linear * 100.0) as u32)),
-        ]),
     ];
 
     let p = Paragraph::new(content)
@@ -206,4 +262,29 @@ fn render_card(f: &mut Frame, app: &App, idx: usize, sound: &Sound, area: Rect)
         .alignment(Alignment::Center);
 
     f.render_widget(p, area);
+
+    // Drawn on its own so it sits exactly where clicks are read
+    let Some(row) = slider_row(area) else {
+        return;
+    };
+    let slider_line = Line::from(vec![
+        Span::styled(
+            slider,
+            Style::default().fg(if sound.error_state {
+                Color::Red
+            } else if playing {
+                if selected {
+                    Color::Black
+                } else {
+                    Color::Green
+                }
+            } else {
+                Color::Blue
+            }),
+        ),
+        Span::raw(format!(" {:>3}%", (position * 100.0).round() as u32)),
+    ]);
+    let cells = slider_cells(row);
+    let line_area = Rect::new(cells.x, row.y, row.right() - cells.x, 1);
+    f.render_widget(Paragraph::new(slider_line), line_area);
 }
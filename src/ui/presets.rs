@@ -1,17 +1,45 @@
 use crate::app::App;
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
+use std::rc::Rc;
 
-pub fn render_presets(f: &mut Frame, app: &App, area: Rect) {
-    let chunks = Layout::default()
+fn presets_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(3), Constraint::Min(0)])
-        .split(area);
+        .split(area)
+}
+
+// The rows of the list inside its border, and the first preset shown so
+// the cursor stays in view
+fn list_rows(app: &App, area: Rect) -> (Rect, usize) {
+    let rows = presets_layout(area)[1].inner(Margin::new(1, 1));
+    let height = rows.height as usize;
+    let offset = if app.preset_cursor_pos >= height {
+        app.preset_cursor_pos - height + 1
+    } else {
+        0
+    };
+    (rows, offset)
+}
+
+/// The preset listed on row `y`, if any.
+pub fn preset_at(app: &App, area: Rect, y: u16) -> Option<usize> {
+    let (rows, offset) = list_rows(app, area);
+    if !(rows.top()..rows.bottom()).contains(&y) {
+        return None;
+    }
+    let index = offset + (y - rows.y) as usize;
+    (index < app.presets_config.presets.len()).then_some(index)
+}
+
+pub fn render_presets(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = presets_layout(area);
 
     let input_style = if app.preset_input_mode {
         Style::default().fg(Color::Yellow)
@@ -48,12 +76,8 @@ pub fn render_presets(f: &mut Frame, app: &App, area: Rect) {
         return;
     }
 
-    let list_height = chunks[1].height.saturating_sub(2) as usize; // Subtract borders
-    let offset = if app.preset_cursor_pos >= list_height {
-        app.preset_cursor_pos - list_height + 1
-    } else {
-        0
-    };
+    let (rows, offset) = list_rows(app, area);
+    let list_height = rows.height as usize;
 
     let mut list_items = Vec::new();
     for (i, preset) in presets.iter().enumerate().skip(offset).take(list_height) {